@@ -64,6 +64,90 @@ impl<'a> Client<'a> {
             .map_err(anyhow::Error::new)
     }
 
+    pub async fn get_text<U>(&self, url: U) -> Result<String>
+    where
+        U: Into<String>,
+    {
+        let u = self.base_url.parse(&url.into()).map_err(anyhow::Error::new)?;
+
+        info!("GET {u}");
+
+        self
+            .http_client
+            .get(u)
+            .bearer_auth(self.config.auth_token())
+            .header(header::ACCEPT, "text/plain")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+            .map_err(anyhow::Error::new)
+    }
+
+    /// The body from byte `from` on, requested with a `Range` header. Servers that ignore it
+    /// send the whole body, which is cut here, and an offset past the end gives an empty body.
+    pub async fn get_bytes_from<U>(&self, url: U, from: usize) -> Result<Vec<u8>>
+    where
+        U: Into<String>,
+    {
+        let u = self.base_url.parse(&url.into()).map_err(anyhow::Error::new)?;
+
+        info!("GET {u} from {from}");
+
+        let response = self
+            .http_client
+            .get(u)
+            .bearer_auth(self.config.auth_token())
+            .header(header::ACCEPT, "text/plain")
+            .header(header::RANGE, format!("bytes={from}-"))
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(Vec::new());
+        }
+
+        let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let body = response.error_for_status()?.bytes().await?;
+
+        Ok(match partial {
+            true => body.to_vec(),
+            false => body.get(from..).unwrap_or_default().to_vec(),
+        })
+    }
+
+    /// Streams the response body into `writer`, reporting the size of each received chunk.
+    pub async fn download<U, W, F>(&self, url: U, writer: &mut W, mut on_chunk: F) -> Result<u64>
+    where
+        U: Into<String>,
+        W: std::io::Write,
+        F: FnMut(u64),
+    {
+        let u = self.base_url.parse(&url.into()).map_err(anyhow::Error::new)?;
+
+        info!("GET {u}");
+
+        let mut response = self
+            .http_client
+            .get(u)
+            .bearer_auth(self.config.auth_token())
+            .header(header::ACCEPT, "*/*")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut total = 0;
+
+        while let Some(chunk) = response.chunk().await? {
+            writer.write_all(&chunk)?;
+            total += chunk.len() as u64;
+            on_chunk(chunk.len() as u64);
+        }
+
+        Ok(total)
+    }
+
     pub async fn post<B, R, U>(&self, url: U, body: &B) -> Result<R>
     where
         U: Into<String>,
@@ -89,4 +173,73 @@ impl<'a> Client<'a> {
             .await
             .map_err(anyhow::Error::new)
     }
+
+    pub async fn put<B, R, U>(&self, url: U, body: &B) -> Result<R>
+    where
+        U: Into<String>,
+        B: serde::Serialize + std::fmt::Debug + ?Sized,
+        R: serde::de::DeserializeOwned
+    {
+        let u = self.base_url.parse(&url.into()).map_err(anyhow::Error::new)?;
+
+        #[cfg(windows)]
+        let _enabled = colored_json::enable_ansi_support();
+
+        info!("PUT {u}\n{}", serde_json::to_value(body).and_then(|v| to_colored_json_auto(&v))?);
+
+        self
+            .http_client
+            .put(u)
+            .bearer_auth(self.config.auth_token())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .map_err(anyhow::Error::new)
+    }
+
+    /// Posts a plain text body, returns the plain text response.
+    pub async fn post_text<U>(&self, url: U, body: String) -> Result<String>
+    where
+        U: Into<String>,
+    {
+        let u = self.base_url.parse(&url.into()).map_err(anyhow::Error::new)?;
+
+        info!("POST {u}");
+
+        self
+            .http_client
+            .post(u)
+            .bearer_auth(self.config.auth_token())
+            .header(header::CONTENT_TYPE, "text/plain")
+            .header(header::ACCEPT, "text/plain")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+            .map_err(anyhow::Error::new)
+    }
+
+    pub async fn delete<U>(&self, url: U) -> Result<()>
+    where
+        U: Into<String>,
+    {
+        let u = self.base_url.parse(&url.into()).map_err(anyhow::Error::new)?;
+
+        info!("DELETE {u}");
+
+        self
+            .http_client
+            .delete(u)
+            .bearer_auth(self.config.auth_token())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
 }
@@ -6,6 +6,7 @@ extern crate colored_json;
 extern crate skim;
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
 use clap::{Command, CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Generator, Shell};
 use clap_verbosity_flag::Verbosity;
@@ -24,6 +25,9 @@ mod youtrack;
 mod table;
 
 use crate::settings::*;
+use crate::teamcity::build::{Property, RunBuildOptions};
+use crate::teamcity::build_locator::{parse_date, BuildLocatorBuilder, BuildState, BuildStatus};
+use crate::teamcity::paging::Limit;
 use crate::teamcity::ArgBuildType;
 use crate::youtrack::issue::BranchNameWithIssueId;
 
@@ -47,18 +51,183 @@ enum Commands {
     RunBuild {
         #[arg(short, long)]
         branch_name: Option<String>,
+        /// build exactly this revision, local HEAD is used by default when it is pushed
+        #[arg(short, long, conflicts_with = "branch_name")]
+        commit: Option<String>,
+        /// wait until the build is finished, exits with non-zero code if it fails
+        #[arg(short, long)]
+        wait: bool,
+        /// custom build parameter, e.g. -p env.FEATURE=on
+        #[arg(short = 'p', long = "param", value_name = "NAME=VALUE")]
+        params: Vec<Property>,
+        /// ask for the build type's parameters
+        #[arg(short, long)]
+        interactive: bool,
+        /// how long to wait for TeamCity to detect the pushed revision, 0 to skip the VCS check
+        #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+        vcs_timeout: u64,
+        /// reuse successful builds of the same revision for direct snapshot dependencies
+        #[arg(long)]
+        reuse_deps: bool,
+        /// a tag to add to the build, could be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        #[arg(long)]
+        comment: Option<String>,
+        /// build the working tree, including uncommitted changes, as a personal build
+        #[arg(long, conflicts_with_all = ["commit", "branch_name"])]
+        personal: bool,
+    },
+
+    #[command()]
+    WatchBuild { id: i32 },
+
+    /// Queues a build (or a deploy) with the same revision and parameters as the given one
+    #[command()]
+    Rerun {
+        #[arg(required_unless_present = "last_failed")]
+        build_id: Option<i32>,
+        /// rerun the last failed build of the current branch
+        #[arg(long, conflicts_with = "build_id")]
+        last_failed: bool,
+        #[arg(short, long)]
+        wait: bool,
+    },
+
+    /// Builds the current branch and deploys the build once it succeeds
+    #[command()]
+    Ship {
+        /// comma separated environments, pick them interactively when omitted
+        #[arg(short, long, value_delimiter = ',')]
+        env: Vec<String>,
+        /// wait until the deploy is finished too
+        #[arg(short, long)]
+        wait: bool,
+        /// how long to wait for TeamCity to detect the pushed revision, 0 to skip the VCS check
+        #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+        vcs_timeout: u64,
+    },
+
+    /// Shows success rate, durations and their daily trend for recent builds
+    #[command()]
+    Stats {
+        /// a build type id, a build type associated with workdir is using by default
+        #[arg(long)]
+        build_type: Option<String>,
+        /// use "any" as a value to disable filter, current branch name is using by default
+        #[arg(long)]
+        branch_name: Option<String>,
+        /// a number of builds, or "all"
+        #[arg(short, long, default_value_t = Limit::Count(100))]
+        limit: Limit,
+        /// a buildStatistics value to add as a column, e.g. CodeCoverageL% or TotalTestCount
+        #[arg(short, long = "stat", value_name = "NAME")]
+        stats: Vec<String>,
+    },
+
+    /// Pins, tags and untags builds
+    #[command()]
+    Build {
+        #[command(subcommand)]
+        command: BuildCommands,
+    },
+
+    /// Shows the snapshot dependencies of a build as a tree
+    #[command()]
+    Chain { build_id: i32 },
+
+    /// Shows or edits a build type's configuration
+    #[command()]
+    BuildType {
+        #[command(subcommand)]
+        command: BuildTypeCommands,
+    },
+
+    /// Cancels a running build or removes a queued one
+    #[command()]
+    CancelBuild {
+        /// select among my queued or running builds of the current branch, when omitted
+        id: Option<i32>,
+        #[arg(short, long)]
+        comment: Option<String>,
+    },
+
+    /// Shows the failed blocks of a build log
+    #[command()]
+    BuildLog {
+        id: i32,
+        /// print the last N lines of the log instead of the failed blocks
+        #[arg(short, long)]
+        tail: Option<usize>,
+        /// keep printing new lines until the build is finished
+        #[arg(short, long)]
+        follow: bool,
     },
 
     #[command()]
     RunDeploy {
+        /// a build id, or one of: master, last-green:<branch>, tag:<tag>, pinned
         #[arg(short, long, conflicts_with = "branch_name")]
         build_id: Option<String>,
         #[arg(long)]
         branch_name: Option<String>,
+        /// comma separated environments, pick them interactively when omitted
+        #[arg(short, long, value_delimiter = ',')]
+        env: Vec<String>,
+        /// deploy to the environments one by one, stopping at the first failed one
+        #[arg(long, requires = "wait")]
+        sequential: bool,
+        /// wait until the deploys are finished
+        #[arg(short, long)]
+        wait: bool,
+        /// custom build parameter, e.g. -p env.FEATURE=on
+        #[arg(short = 'p', long = "param", value_name = "NAME=VALUE")]
+        params: Vec<Property>,
+        /// ask for the build type's parameters
+        #[arg(short, long)]
+        interactive: bool,
+        /// how long to wait for TeamCity to detect the pushed branch when no build id is given, 0 to skip the VCS check
+        #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+        vcs_timeout: u64,
+    },
+
+    /// Shows the build queue for the current repo's build types
+    #[command()]
+    Queue {
+        /// show builds triggered by me instead
+        #[arg(long)]
+        my: bool,
+        /// move my queued build to the top of the queue
+        #[arg(long, value_name = "BUILD_ID")]
+        top: Option<i32>,
+    },
+
+    /// Lists investigations assigned to me or to another user
+    #[command()]
+    Investigations {
+        /// a username, or a part of a name to pick the user from
         #[arg(short, long)]
-        env: Option<String>,
+        user: Option<String>,
+        #[command(subcommand)]
+        command: Option<InvestigationsCommands>,
+    },
+
+    /// Lists muted tests and problems of the current repo's build types
+    #[command()]
+    Mutes {},
+
+    /// Lists build agents with their state and the builds they are running
+    #[command()]
+    Agents {
+        /// show which agents can run the build type, the one associated with workdir by default
+        #[arg(long, value_name = "BUILD_TYPE", num_args = 0..=1)]
+        compatible: Option<Option<String>>,
     },
 
+    /// Shows what is deployed to each environment of the current repo
+    #[command()]
+    Environments {},
+
     #[command()]
     ListBuilds {
         #[arg(short, long, conflicts_with_all = ["branch_name", "build_type", "master"])]
@@ -78,8 +247,68 @@ enum Commands {
         /// default.
         #[arg(long)]
         author: Option<String>,
-        #[arg(short, long)]
-        limit: Option<u8>,
+        /// a number of builds, or "all"
+        #[arg(short, long, default_value_t = Limit::Count(5))]
+        limit: Limit,
+        /// only failed builds
+        #[arg(long, conflicts_with = "status")]
+        failed: bool,
+        #[arg(long, value_enum)]
+        status: Option<BuildStatus>,
+        /// only running builds
+        #[arg(long, conflicts_with = "state")]
+        running: bool,
+        #[arg(long, value_enum)]
+        state: Option<BuildState>,
+        /// builds started after the date, e.g. 2d, 12h, 1w or 2023-01-31
+        #[arg(long, value_parser = parse_date)]
+        since: Option<DateTime<FixedOffset>>,
+        /// builds started before the date, e.g. 2d, 12h, 1w or 2023-01-31
+        #[arg(long, value_parser = parse_date)]
+        until: Option<DateTime<FixedOffset>>,
+        /// builds of the revision, e.g. HEAD or a commit sha
+        #[arg(long)]
+        commit: Option<String>,
+        #[arg(long)]
+        tag: Option<String>,
+        #[arg(long)]
+        pinned: bool,
+        #[arg(long)]
+        agent: Option<String>,
+        #[arg(long)]
+        failed_to_start: bool,
+        /// show personal builds instead
+        #[arg(long)]
+        personal: bool,
+    },
+
+    /// Lists VCS changes of a build with their YouTrack issues
+    #[command()]
+    BuildChanges {
+        build_id: i32,
+        /// list the changes which are not deployed to the environment yet
+        #[arg(long, value_name = "ENV")]
+        since_deployed: Option<String>,
+    },
+
+    /// Lists artifacts of a build, the last green build of the current branch by default
+    #[command(args_conflicts_with_subcommands = true)]
+    Artifacts {
+        build_id: Option<i32>,
+        /// list files inside archives too
+        #[arg(long)]
+        archives: bool,
+        #[command(subcommand)]
+        command: Option<ArtifactsCommands>,
+    },
+
+    /// Lists failed, muted and ignored tests of a build
+    #[command()]
+    Tests {
+        build_id: i32,
+        /// show only the tests which started failing since the previous build on the same branch
+        #[arg(long)]
+        new: bool,
     },
 
     #[command()]
@@ -116,6 +345,106 @@ enum Commands {
     Init {},
 }
 
+#[derive(Debug, Subcommand)]
+enum BuildCommands {
+    /// Pins the build, so it isn't cleaned up
+    #[command()]
+    Pin {
+        id: i32,
+        #[arg(short, long)]
+        comment: Option<String>,
+    },
+    #[command()]
+    Unpin {
+        id: i32,
+        #[arg(short, long)]
+        comment: Option<String>,
+    },
+    #[command()]
+    Tag {
+        id: i32,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    #[command()]
+    Untag {
+        id: i32,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum BuildTypeCommands {
+    /// Prints settings, parameters, VCS roots, triggers and snapshot dependencies
+    #[command()]
+    Show {
+        /// a build type id, a build type associated with workdir is using by default
+        id: Option<String>,
+    },
+    #[command()]
+    Param {
+        #[command(subcommand)]
+        command: ParamCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ParamCommands {
+    /// Sets the build type's own parameter
+    #[command()]
+    Set {
+        name: String,
+        value: String,
+        /// a build type id, a build type associated with workdir is using by default
+        #[arg(long)]
+        build_type: Option<String>,
+    },
+    /// Removes the build type's own parameter
+    #[command()]
+    Unset {
+        name: String,
+        #[arg(long)]
+        build_type: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum InvestigationsCommands {
+    /// Takes responsibility for the investigation
+    #[command()]
+    Take {
+        /// select among the listed investigations, when omitted
+        id: Option<String>,
+    },
+    /// Gives up the investigation
+    #[command()]
+    GiveUp { id: Option<String> },
+    /// Marks the investigation fixed
+    #[command()]
+    Fixed { id: Option<String> },
+}
+
+#[derive(Debug, Subcommand)]
+enum ArtifactsCommands {
+    /// Downloads artifacts matching the glob, use `archive.zip!/path` for files inside archives
+    #[command()]
+    Get {
+        #[arg(value_name = "PATH_GLOB")]
+        glob: String,
+        /// the last green build of the current branch by default
+        #[arg(short, long)]
+        build_id: Option<i32>,
+        #[arg(short, long, default_value = ".", value_hint = clap::ValueHint::DirPath)]
+        output: std::path::PathBuf,
+    },
+}
+
+/// `None` disables the VCS check before queueing.
+fn vcs_check_timeout(seconds: u64) -> Option<std::time::Duration> {
+    (seconds > 0).then(|| std::time::Duration::from_secs(seconds))
+}
+
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
 }
@@ -141,25 +470,639 @@ async fn main() -> Result<()> {
         let teamcity = teamcity::Client::new(&config.teamcity, &repo)?;
 
         match command {
-            Commands::RunBuild { branch_name } => {
-                let build = teamcity.run_build(None, branch_name.as_deref()).await?;
+            Commands::RunBuild {
+                branch_name,
+                commit,
+                wait,
+                params,
+                interactive,
+                vcs_timeout,
+                reuse_deps,
+                tags,
+                comment,
+                personal,
+            } => {
+                // a personal build is the upstream revision with the local changes on top
+                let (base, personal_patch) = if personal {
+                    let (base, patch) = repo.diff_against_upstream()?;
+
+                    (Some(base), Some(patch))
+                } else {
+                    (None, None)
+                };
+
+                let revision = match (commit, &branch_name) {
+                    _ if personal => base,
+                    (Some(rev), _) => {
+                        let revision = repo.resolve_commit(&rev)?;
+
+                        if !repo.is_pushed(&revision).unwrap_or(false) {
+                            eprintln!("{}", style(format!("⚠ {rev} is not pushed, TeamCity won't be able to check it out")).yellow());
+                        }
+
+                        Some(revision)
+                    }
+                    (None, None) => match repo.is_pushed("HEAD") {
+                        Ok(true) => Some(repo.resolve_commit("HEAD")?),
+                        _ => {
+                            eprintln!("{}", style("⚠ HEAD is not pushed, the last pushed commit of the branch will be built").yellow());
+                            None
+                        }
+                    },
+                    (None, Some(_)) => None,
+                };
+
+                // in a monorepo every service touched by the branch gets its own build
+                let build_types = teamcity.affected_build_types(branch_name.as_deref(), revision.as_deref(), personal)?;
+                let mut builds = Vec::with_capacity(build_types.len());
+                let mut failed = Vec::new();
+
+                for build_type in build_types {
+                    let result = teamcity
+                        .run_build(RunBuildOptions {
+                            build_type: Some(build_type),
+                            branch_name: branch_name.as_deref(),
+                            revision: revision.as_deref(),
+                            properties: params.clone(),
+                            interactive,
+                            vcs_timeout: vcs_check_timeout(vcs_timeout),
+                            reuse_dependencies: reuse_deps,
+                            personal_patch: personal_patch.clone(),
+                            tags: tags.clone(),
+                            comment: comment.as_deref(),
+                        })
+                        .await;
+
+                    match result {
+                        Ok(build) => {
+                            println!("{} {}", style(build_type).dim(), style(&build.web_url).bold().blue());
+                            builds.push(build);
+                        }
+                        Err(err) => {
+                            eprintln!("{} {build_type}: {err:#}", style("✘ failed to queue").red());
+                            failed.push(build_type);
+                        }
+                    }
+                }
+
+                if !builds.is_empty() {
+                    let urls: Vec<&str> = builds.iter().map(|b| b.web_url.as_str()).collect();
+                    let _ = dump_to_clipboard(&urls.join("\n"));
+                    println!("{}", style("✔ copied!").green().italic());
+                }
+
+                if wait {
+                    for build in &builds {
+                        watch_build(&teamcity, build.id()).await?;
+                    }
+                }
+
+                if !failed.is_empty() {
+                    anyhow::bail!("Queued {} of {} build types, failed: {}", builds.len(), builds.len() + failed.len(), failed.join(", "));
+                }
+            }
+
+            Commands::WatchBuild { id } => {
+                watch_build(&teamcity, id).await?;
+            }
+
+            Commands::Rerun { build_id, last_failed, wait } => {
+                let id = match build_id {
+                    Some(id) => id,
+                    None if last_failed => teamcity.get_last_failed_build_id(None).await?,
+                    None => unreachable!(),
+                };
+
+                let build = teamcity.rerun_build(id).await?;
 
                 println!("{}", style(&build.web_url).bold().blue());
 
-                let _ = dump_to_clipboard(&build.web_url);
-                println!("{}", style("✔ copied!").green().italic());
+                if wait {
+                    watch_build(&teamcity, build.id()).await?;
+                }
+            }
+
+            Commands::Ship { env, wait, vcs_timeout } => {
+                let revision = repo
+                    .is_pushed("HEAD")
+                    .context("Cannot find the upstream of the current branch, push it first")?
+                    .then(|| repo.resolve_commit("HEAD"))
+                    .transpose()?
+                    .context("HEAD is not pushed, push it first")?;
+
+                let build = teamcity
+                    .run_build(RunBuildOptions {
+                        revision: Some(&revision),
+                        vcs_timeout: vcs_check_timeout(vcs_timeout),
+                        ..RunBuildOptions::default()
+                    })
+                    .await?;
+
+                println!("{} {}", style("▶ build").bold(), style(&build.web_url).blue());
+                watch_build(&teamcity, build.id()).await?;
+
+                let plan = teamcity
+                    .plan_deploy(Some(&build.id().to_string()), None, &env, None)
+                    .await
+                    .context("The build succeeded, but there is nothing to deploy")?;
+
+                for build_type in &plan.build_types {
+                    let deploy = teamcity
+                        .queue_deploy(&plan, build_type, Vec::new(), false)
+                        .await
+                        .with_context(|| format!("The build succeeded, but the deploy to {} cannot be queued", build_type.name()))?;
+
+                    println!("{} {}", style("▶ deploy").bold(), style(&deploy.web_url).blue());
+
+                    if wait {
+                        watch_build(&teamcity, deploy.id()).await?;
+                    }
+                }
+            }
+
+            Commands::Stats { build_type, branch_name, limit, stats: statistics } => {
+                use teamcity::chain::format_duration;
+                use teamcity::stats::{median, percentile, Stats};
+
+                let builds = teamcity
+                    .get_finished_builds(build_type.as_deref(), branch_name.as_deref(), limit)
+                    .await?;
+
+                if builds.is_empty() {
+                    anyhow::bail!("No finished builds found");
+                }
+
+                let stats = Stats::new(&builds, &statistics);
+                let duration = |secs: Option<f64>| {
+                    secs.map(|secs| format_duration(chrono::Duration::seconds(secs as i64)))
+                        .unwrap_or_default()
+                };
+
+                println!("{} builds, {:.0}% successful", stats.builds, stats.success_rate);
+                println!(
+                    "run time: median {}, p95 {}",
+                    duration(median(&stats.run_secs)),
+                    duration(percentile(&stats.run_secs, 95.0)),
+                );
+                println!(
+                    "queue time: median {}, p95 {}",
+                    duration(median(&stats.queue_secs)),
+                    duration(percentile(&stats.queue_secs, 95.0)),
+                );
+
+                if !stats.slowest_steps.is_empty() {
+                    let steps: Vec<String> = stats
+                        .slowest_steps
+                        .iter()
+                        .map(|(step, secs)| format!("{step} {}", duration(Some(*secs))))
+                        .collect();
+
+                    println!("slowest steps: {}", steps.join(", "));
+                }
+
+                let mut titles = row!["Day", "Builds", "Success", "Run Time", "Queue Time"];
+
+                for name in &statistics {
+                    titles.add_cell(prettytable::Cell::new(name));
+                }
+
+                let mut table = table::Table::new(titles);
+
+                for day in &stats.days {
+                    let mut row = row![
+                        day.day,
+                        day.builds,
+                        format!("{:.0}%", day.success_rate),
+                        duration(day.run_secs),
+                        duration(day.queue_secs),
+                    ];
+
+                    for value in &day.statistics {
+                        row.add_cell(prettytable::Cell::new(&value.map(|v| v.to_string()).unwrap_or_default()));
+                    }
+
+                    table.add_row(row);
+                }
+
+                let mut trend = row![
+                    style("Trend").bold(),
+                    table::sparkline(&stats.days.iter().map(|d| Some(d.builds as f64)).collect::<Vec<_>>()),
+                    table::sparkline(&stats.days.iter().map(|d| Some(d.success_rate)).collect::<Vec<_>>()),
+                    table::sparkline(&stats.days.iter().map(|d| d.run_secs).collect::<Vec<_>>()),
+                    table::sparkline(&stats.days.iter().map(|d| d.queue_secs).collect::<Vec<_>>()),
+                ];
+
+                for i in 0..statistics.len() {
+                    let values: Vec<Option<f64>> = stats.days.iter().map(|d| d.statistics[i]).collect();
+                    trend.add_cell(prettytable::Cell::new(&table::sparkline(&values)));
+                }
+
+                table.add_row(trend);
+                table.printstd();
+            }
+
+            Commands::Build { command } => {
+                let id = match command {
+                    BuildCommands::Pin { id, comment } => {
+                        teamcity.pin_build(id, true, comment.as_deref()).await?;
+                        id
+                    }
+                    BuildCommands::Unpin { id, comment } => {
+                        teamcity.pin_build(id, false, comment.as_deref()).await?;
+                        id
+                    }
+                    BuildCommands::Tag { id, tags } => {
+                        teamcity.tag_build(id, &tags).await?;
+                        id
+                    }
+                    BuildCommands::Untag { id, tags } => {
+                        teamcity.untag_build(id, &tags).await?;
+                        id
+                    }
+                };
+
+                println!("{} #{id}", style("✔ updated").green());
+            }
+
+            Commands::BuildType { command: BuildTypeCommands::Show { id } } => {
+                let build_type = teamcity.get_build_type(id.as_deref()).await?;
+
+                println!("{} {}", style(build_type.name()).bold(), style(build_type.id()).dim());
+                println!("{:<8}{}", "Project", build_type.project_name());
+                println!("{:<8}{}", "Type", build_type.kind());
+                if build_type.is_paused() {
+                    println!("{:<8}{}", "State", style("paused").yellow());
+                }
+                if let Some(description) = build_type.description() {
+                    println!("{description}");
+                }
+                println!("{}", build_type.web_url());
+
+                let mut settings = table::Table::new(row!["Setting", "Value"]);
+                for setting in build_type.settings() {
+                    settings.add_row(row![setting.name, setting.value]);
+                }
+                settings.printstd();
+
+                let mut parameters = table::Table::new(row!["Parameter", "Value", "Inherited"]);
+                for parameter in build_type.parameters() {
+                    let inherited = if parameter.is_inherited() { "yes" } else { "" };
+                    parameters.add_row(row![parameter.name(), parameter.value(), inherited]);
+                }
+                parameters.printstd();
+
+                let mut vcs_roots = table::Table::new(row!["VCS Root", "Checkout Rules"]);
+                for entry in build_type.vcs_root_entries() {
+                    vcs_roots.add_row(row![entry.name(), entry.checkout_rules()]);
+                }
+                vcs_roots.printstd();
+
+                let mut triggers = table::Table::new(row!["Trigger", "Settings"]);
+                for trigger in build_type.triggers() {
+                    let kind = match trigger.is_disabled() {
+                        true => style(format!("{} (disabled)", trigger.kind())).dim(),
+                        false => style(trigger.kind().to_string()),
+                    };
+                    triggers.add_row(row![kind, trigger.describe()]);
+                }
+                triggers.printstd();
+
+                let mut dependencies = table::Table::new(row!["Snapshot Dependency", "Name"]);
+                for dependency in build_type.snapshot_dependencies() {
+                    dependencies.add_row(row![dependency.build_type_id(), dependency.build_type_name()]);
+                }
+                dependencies.printstd();
+            }
+
+            Commands::BuildType { command: BuildTypeCommands::Param { command } } => match command {
+                ParamCommands::Set { name, value, build_type } => {
+                    let build_type = build_type.as_deref().or(teamcity.build_type).context("No build type for current repo")?;
+                    let parameter = teamcity.set_build_type_parameter(build_type, &name, &value).await?;
+
+                    println!("{} {}={} in {build_type}", style("✔ set").green(), parameter.name(), parameter.value());
+                }
+                ParamCommands::Unset { name, build_type } => {
+                    let build_type = build_type.as_deref().or(teamcity.build_type).context("No build type for current repo")?;
+                    teamcity.unset_build_type_parameter(build_type, &name).await?;
+
+                    println!("{} {name} in {build_type}", style("✔ removed").green());
+                }
+            },
+
+            Commands::Chain { build_id } => {
+                let builds = teamcity.get_chain(build_id).await?;
+
+                for (prefix, build) in teamcity::chain::tree_lines(&builds, build_id) {
+                    let state = match (build.state(), build.status()) {
+                        ("finished", Some("SUCCESS")) => style("").bold().green(),
+                        ("finished", _) => style("").bold().red(),
+                        (state, _) => style(state).yellow(),
+                    };
+
+                    println!(
+                        "{prefix}{state} {} #{} {}",
+                        build.build_type_id(),
+                        build.number(),
+                        style(build.duration().map(teamcity::chain::format_duration).unwrap_or_default()).dim(),
+                    );
+                }
+            }
+
+            Commands::CancelBuild { id, comment } => {
+                let ids = match id {
+                    Some(id) => vec![id],
+                    None => {
+                        let builds = teamcity.get_active_builds(None).await?;
+
+                        if builds.is_empty() {
+                            anyhow::bail!("No queued or running builds found");
+                        }
+
+                        normalize::select_many(builds, None)?
+                            .iter()
+                            .map(|b| b.id)
+                            .collect()
+                    }
+                };
+
+                for id in ids {
+                    teamcity.cancel_build(id, comment.as_deref()).await?;
+                    println!("{} #{id}", style("✔ canceled").green());
+                }
+            }
+
+            Commands::BuildLog { id, tail, follow } => {
+                let log = teamcity.get_build_log(id).await?;
+
+                if tail.is_some() || follow {
+                    let lines: Vec<&str> = log.lines().collect();
+                    let from = lines.len().saturating_sub(tail.unwrap_or(10));
+
+                    for line in &lines[from..] {
+                        println!("{line}");
+                    }
+
+                    if follow {
+                        teamcity.follow_build_log(id, log.len(), |line| println!("{line}")).await?;
+                    }
+                } else {
+                    let lines = teamcity::build_log::parse_log(&log);
+                    let failed = teamcity::build_log::collapse_to_failed(&lines);
+
+                    if failed.is_empty() {
+                        println!("{}", style("No failed blocks found").green());
+                    }
+
+                    for line in failed {
+                        if line.is_error() {
+                            println!("{}", style(line.text()).red());
+                        } else {
+                            println!("{}", line.text());
+                        }
+                    }
+                }
             }
 
             Commands::RunDeploy {
                 build_id,
                 env,
                 branch_name,
+                sequential,
+                wait,
+                params,
+                interactive,
+                vcs_timeout,
             } => {
-                let response = teamcity
-                    .run_deploy(build_id.as_deref(), env.as_deref(), branch_name.as_deref())
+                let plan = teamcity
+                    .plan_deploy(build_id.as_deref(), branch_name.as_deref(), &env, vcs_check_timeout(vcs_timeout))
                     .await?;
 
-                println!("{}", response.web_url);
+                let mut queued = Vec::new();
+                let mut failed = None;
+
+                for build_type in &plan.build_types {
+                    let response = match teamcity.queue_deploy(&plan, build_type, params.clone(), interactive).await {
+                        Ok(response) => response,
+                        Err(err) => {
+                            failed = Some(err.context(format!("Cannot queue the deploy to {}", build_type.name())));
+                            break;
+                        }
+                    };
+
+                    let result = match sequential {
+                        true => watch_build(&teamcity, response.id()).await,
+                        false => Ok(()),
+                    };
+
+                    queued.push((build_type, response));
+
+                    if let Err(err) = result {
+                        failed = Some(err);
+                        break;
+                    }
+                }
+
+                let mut table = table::Table::new(row!["Environment", "Url"]);
+
+                for build_type in &plan.build_types {
+                    let url = match queued.iter().find(|(bt, _)| bt.id == build_type.id) {
+                        Some((_, response)) => style(response.web_url.as_str()).blue().underlined().to_string(),
+                        None => style("skipped").dim().to_string(),
+                    };
+
+                    table.add_row(row![build_type.name(), url]);
+                }
+
+                println!("#{}", plan.build_number());
+                table.printstd();
+
+                if let Some(err) = failed {
+                    return Err(err);
+                }
+
+                if wait && !sequential {
+                    for (_, response) in &queued {
+                        watch_build(&teamcity, response.id()).await?;
+                    }
+                }
+            }
+
+            Commands::Queue { my, top } => {
+                if let Some(id) = top {
+                    teamcity.move_to_top(id).await?;
+                    println!("{} #{id}", style("✔ moved to the top").green());
+                }
+
+                let queue = teamcity.get_queue(my).await?;
+
+                if queue.is_empty() {
+                    println!("{}", style("The queue is empty").green());
+                    return Ok(());
+                }
+
+                let mut table = table::Table::new(row![
+                    "#",
+                    "Build Type",
+                    "Url (branch)",
+                    "Triggered By",
+                    "Wait Reason",
+                    "Agents",
+                    "Estimated Start",
+                ]);
+
+                for build in &queue {
+                    let agents = match build.compatible_agents() {
+                        Some(0) => style("0".to_string()).red().to_string(),
+                        Some(count) => count.to_string(),
+                        None => String::new(),
+                    };
+
+                    table.add_row(row![
+                        build.position(),
+                        build.build_type_id(),
+                        format!(
+                            "{url}\n{branch}",
+                            url = style(build.web_url()).blue().underlined(),
+                            branch = build.branch_name().unwrap_or("master (default branch)"),
+                        ),
+                        build.triggered_by(),
+                        build.wait_reason(),
+                        agents,
+                        build.start_estimate(),
+                    ]);
+                }
+
+                table.printstd();
+            }
+
+            Commands::Investigations { user, command } => {
+                use teamcity::investigation::Resolution;
+
+                let investigations = teamcity.get_investigations(user.as_deref()).await?;
+
+                let (id, resolution) = match command {
+                    None => {
+                        let mut table = table::Table::new(row!["Build Type", "Investigating", "Assignee", "Comment", "Since"]);
+
+                        for investigation in &investigations {
+                            table.add_row(row![
+                                investigation.scope().describe(),
+                                investigation.target().describe(),
+                                investigation.assignee(),
+                                investigation.comment(),
+                                investigation.assigned_at(),
+                            ]);
+                        }
+
+                        table.printstd();
+
+                        return Ok(());
+                    }
+                    Some(InvestigationsCommands::Take { id }) => (id, Resolution::Take),
+                    Some(InvestigationsCommands::GiveUp { id }) => (id, Resolution::GiveUp),
+                    Some(InvestigationsCommands::Fixed { id }) => (id, Resolution::Fixed),
+                };
+
+                let id = match id {
+                    Some(id) => id,
+                    None => normalize::select_one(investigations, None)?.id().to_string(),
+                };
+
+                teamcity.resolve_investigation(&id, resolution).await?;
+
+                println!("{} {id}", style("✔ updated").green());
+            }
+
+            Commands::Mutes {} => {
+                let mutes = teamcity.get_mutes().await?;
+
+                let mut table = table::Table::new(row!["Muted", "Scope", "Muted By", "Comment", "Since"]);
+
+                for mute in &mutes {
+                    table.add_row(row![
+                        mute.target().describe(),
+                        mute.scope().describe(),
+                        mute.muted_by(),
+                        mute.comment(),
+                        mute.muted_at(),
+                    ]);
+                }
+
+                table.printstd();
+            }
+
+            Commands::Agents { compatible: None } => {
+                let agents = teamcity.get_agents().await?;
+
+                let mut table = table::Table::new(row!["Agent", "Pool", "State", "Running"]);
+
+                for agent in &agents {
+                    let state = match agent.state() {
+                        "connected" => style(agent.state()).green(),
+                        state => style(state).dim(),
+                    };
+
+                    table.add_row(row![agent.name(), agent.pool(), state, agent.running_build().unwrap_or_default()]);
+                }
+
+                table.printstd();
+            }
+
+            Commands::Agents { compatible: Some(build_type) } => {
+                let build_type = build_type.as_deref().or(teamcity.build_type).context("No build type for current repo")?;
+                let agents = teamcity.get_agent_compatibility(Some(build_type)).await?;
+
+                let mut table = table::Table::new(row!["Agent", "Pool", "State", "Compatible", "Unmet Requirements"]);
+
+                for agent in &agents.compatible {
+                    table.add_row(row![agent.name(), agent.pool(), agent.state(), style("✔").green(), ""]);
+                }
+
+                for agent in &agents.incompatible {
+                    table.add_row(row![
+                        agent.name(),
+                        agent.pool(),
+                        agent.state(),
+                        style("✘").red(),
+                        agent.unmet_requirements(build_type).join("\n"),
+                    ]);
+                }
+
+                table.printstd();
+            }
+
+            Commands::Environments {} => {
+                let environments = teamcity.environments().await?;
+
+                let mut table = table::Table::new(row![
+                    "Environment",
+                    "Build",
+                    "Branch",
+                    "Triggered By",
+                    "Deployed",
+                ]);
+
+                for env in &environments {
+                    let build = match &env.deployment {
+                        Some(deployment) if env.outdated => format!(
+                            "{} {}",
+                            style(format!("#{}", deployment.build_number())).yellow(),
+                            style("(not the latest master)").yellow().italic()
+                        ),
+                        Some(deployment) => format!("#{}", deployment.build_number()),
+                        None => style("never deployed").dim().to_string(),
+                    };
+
+                    table.add_row(row![
+                        env.build_type.name(),
+                        build,
+                        env.deployment.as_ref().and_then(|d| d.branch_name()).unwrap_or_default(),
+                        env.deployment.as_ref().map(|d| d.triggered_by()).unwrap_or_default(),
+                        env.deployment.as_ref().map(|d| d.finished_at()).unwrap_or_default(),
+                    ]);
+                }
+
+                table.printstd();
             }
 
             Commands::ListBuilds {
@@ -170,6 +1113,18 @@ async fn main() -> Result<()> {
                 mut build_type,
                 mut author,
                 limit,
+                failed,
+                mut status,
+                running,
+                mut state,
+                since,
+                until,
+                commit,
+                tag,
+                pinned,
+                agent,
+                failed_to_start,
+                personal,
             } => {
                 if any {
                     branch_name.replace("any".into());
@@ -182,11 +1137,36 @@ async fn main() -> Result<()> {
                     author.replace("current".into());
                 }
 
+                if failed {
+                    status.replace(BuildStatus::Failure);
+                }
+
+                if running {
+                    state.replace(BuildState::Running);
+                }
+
+                let revision = commit
+                    .map(|rev| repo.resolve_commit(&rev).unwrap_or(rev));
+
+                let mut filter = BuildLocatorBuilder::default();
+                filter
+                    .status(status)
+                    .state(state)
+                    .since_date(since)
+                    .until_date(until)
+                    .revision(revision.as_deref())
+                    .tag(tag.as_deref())
+                    .pinned(pinned.then_some(true))
+                    .agent_name(agent.as_deref())
+                    .failed_to_start(failed_to_start.then_some(true))
+                    .personal(Some(personal));
+
                 let builds = teamcity
                     .get_builds(
                         branch_name.as_deref(),
                         build_type.as_ref(),
                         author.as_deref(),
+                        filter,
                         limit,
                     )
                     .await?;
@@ -196,7 +1176,9 @@ async fn main() -> Result<()> {
                     "Build Type",
                     "Build Id",
                     "Url (branch)",
-                    "Triggered By"
+                    "Triggered By",
+                    "Pin",
+                    "Tags"
                 ]);
 
                 for build in &builds {
@@ -218,13 +1200,169 @@ async fn main() -> Result<()> {
                             url = style(build.web_url()).blue().underlined(),
                             branch = build.branch_name().unwrap_or("master (default branch)"),
                         ),
-                        build.triggered_by()
+                        build.triggered_by(),
+                        if build.is_pinned() { "📌" } else { "" },
+                        build.tags().join(", ")
                     ]);
                 }
 
                 table.printstd();
             }
 
+            Commands::BuildChanges { build_id, since_deployed } => {
+                let yt_client = crate::youtrack::Client::new(&config.youtrack)?;
+
+                let build_changes = match since_deployed {
+                    Some(env) => teamcity.get_changes_since_deployed(build_id, &env).await?,
+                    None => teamcity.get_build_changes(build_id).await?,
+                };
+
+                let branch_issue_id = build_changes.issue_id();
+                let issue_ids: std::collections::BTreeSet<String> = build_changes
+                    .changes
+                    .iter()
+                    .filter_map(|c| c.issue_id().or_else(|| branch_issue_id.clone()))
+                    .collect();
+
+                // an issue could be deleted or belong to another tracker, so it's fine to miss it
+                let issues: std::collections::HashMap<String, IssueShort> = futures::future::join_all(
+                    issue_ids.iter().map(|id| yt_client.get_issue_by_id::<IssueShort, _>(id.as_str())),
+                )
+                .await
+                .into_iter()
+                .zip(issue_ids.iter())
+                .filter_map(|(issue, id)| issue.ok().map(|issue| (id.clone(), issue)))
+                .collect();
+
+                if build_changes.changes.is_empty() {
+                    println!("{}", style("No changes").green());
+                }
+
+                for change in &build_changes.changes {
+                    println!(
+                        "{} {} {} {}",
+                        style(change.short_version()).yellow(),
+                        style(change.author()).bold(),
+                        style(change.date()).dim(),
+                        change.summary(),
+                    );
+
+                    if let Some(issue) = change
+                        .issue_id()
+                        .or_else(|| branch_issue_id.clone())
+                        .and_then(|id| issues.get(&id))
+                    {
+                        println!("    {} {}", style(issue.id_readable()).blue(), issue.summary());
+                    }
+
+                    for file in change.files() {
+                        println!("    {} {}", style(file.change_type()).dim(), file.file());
+                    }
+                }
+            }
+
+            Commands::Artifacts { build_id, archives, command } => {
+                let last_green_build_id = || async {
+                    let branch = repo.normalize_branch_name(None)?;
+
+                    teamcity
+                        .last_green_build_id(&branch)
+                        .await?
+                        .context("No successful builds found on the current branch")
+                };
+
+                match command {
+                    None => {
+                        let build_id = match build_id {
+                            Some(id) => id,
+                            None => last_green_build_id().await?,
+                        };
+
+                        let artifacts = teamcity.get_artifacts(build_id, archives).await?;
+
+                        let mut table = table::Table::new(row!["Path", "Size", "Modified"]);
+
+                        for artifact in &artifacts {
+                            let indent = "  ".repeat(artifact.depth());
+
+                            table.add_row(row![
+                                if artifact.is_dir() {
+                                    format!("{indent}{}/", style(artifact.name()).bold())
+                                } else {
+                                    format!("{indent}{}", artifact.name())
+                                },
+                                artifact.size().map(teamcity::artifact::format_size).unwrap_or_default(),
+                                artifact.modified_at(),
+                            ]);
+                        }
+
+                        table.printstd();
+                    }
+                    Some(ArtifactsCommands::Get { glob, build_id, output }) => {
+                        let build_id = match build_id {
+                            Some(id) => id,
+                            None => last_green_build_id().await?,
+                        };
+
+                        let artifacts = teamcity.find_artifacts(build_id, &glob).await?;
+
+                        if artifacts.is_empty() {
+                            anyhow::bail!("No artifacts match {glob}");
+                        }
+
+                        let total = artifacts.iter().filter_map(|a| a.size()).sum();
+                        let progress = indicatif::ProgressBar::new(total).with_style(
+                            indicatif::ProgressStyle::with_template("{msg} [{bar:40}] {bytes}/{total_bytes} {eta}")?
+                                .progress_chars("=> "),
+                        );
+
+                        for artifact in &artifacts {
+                            progress.set_message(artifact.name().to_string());
+
+                            let path = teamcity
+                                .download_artifact(build_id, artifact, &output, |size| progress.inc(size))
+                                .await?;
+
+                            progress.println(format!("{} {}", style("✔").green(), path.display()));
+                        }
+
+                        progress.finish_and_clear();
+                    }
+                }
+            }
+
+            Commands::Tests { build_id, new } => {
+                let mut tests = teamcity.get_failed_tests(build_id).await?;
+
+                if new {
+                    let previous = match teamcity.get_previous_build_id(build_id).await? {
+                        Some(id) => teamcity.get_failed_tests(id).await?,
+                        None => Vec::new(),
+                    };
+
+                    tests = teamcity::test_occurrence::new_failures(tests, &previous);
+                }
+
+                if tests.is_empty() {
+                    println!("{}", style("No failed tests").green());
+                }
+
+                for test in &tests {
+                    let kind = match test.kind() {
+                        "failed" => style(test.kind()).red(),
+                        kind => style(kind).yellow(),
+                    };
+
+                    println!("{} {}", kind.bold(), test.name());
+
+                    if let Some(details) = test.details() {
+                        for line in details.lines() {
+                            println!("    {}", style(line).dim());
+                        }
+                    }
+                }
+            }
+
             Commands::Init {} => {
                 unimplemented!()
             }
@@ -445,6 +1583,42 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+async fn watch_build(teamcity: &teamcity::Client<'_>, id: i32) -> Result<()> {
+    let term = console::Term::stderr();
+
+    let build = teamcity
+        .watch_build(id, |progress| {
+            let line = match progress.state() {
+                "queued" => format!("{} {}", progress.title(), style("queued").yellow()),
+                "running" => format!(
+                    "{} {} {}% {}{}",
+                    progress.title(),
+                    style("running").yellow(),
+                    progress.percentage().unwrap_or_default(),
+                    progress.eta().map(|secs| format!("ETA {}m{:02}s ", secs / 60, secs % 60)).unwrap_or_default(),
+                    style(progress.stage().unwrap_or_default()).dim(),
+                ),
+                state => format!("{} {}", progress.title(), state),
+            };
+
+            let _ = term.clear_line();
+            let _ = term.write_str(&line);
+        })
+        .await?;
+
+    let _ = term.clear_line();
+
+    if build.is_successful() {
+        println!("{} {} {}", style("").bold().green(), build.title(), build.status_text().unwrap_or_default());
+
+        Ok(())
+    } else {
+        println!("{} {} {}", style("").bold().red(), build.title(), build.status_text().unwrap_or_default());
+
+        anyhow::bail!("Build {} is {}: {}", build.title(), build.status().unwrap_or("UNKNOWN"), build.web_url())
+    }
+}
+
 #[cfg(target_os = "linux")]
 pub fn dump_to_clipboard(value: &str) -> Result<()> {
     use std::{process::{Command, Stdio}, io::{Error, ErrorKind}};
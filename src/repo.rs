@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
 use tracing::debug;
 
@@ -12,7 +12,9 @@ pub struct BranchNameMeta {
 }
 
 pub struct Repo {
-    repo: git2::Repository
+    repo: git2::Repository,
+    /// the directory the repo is discovered from
+    path: PathBuf,
 }
 
 fn normalize(s: impl Into<String>) -> Option<String> {
@@ -24,9 +26,16 @@ fn normalize(s: impl Into<String>) -> Option<String> {
 impl Repo  {
     pub fn new(path: Option<&Path>) -> Result<Self> {
         let path = crate::normalize::normalize_path(path)?;
-        let repo = git2::Repository::discover(path)?;
+        let repo = git2::Repository::discover(&path)?;
 
-        Ok(Self { repo })
+        Ok(Self { repo, path })
+    }
+
+    /// The directory the repo is discovered from, relative to its root.
+    pub fn relative_path(&self) -> Result<PathBuf> {
+        let workdir = self.repo.workdir().context("Bare repositories have no working directory")?.canonicalize()?;
+
+        Ok(self.path.canonicalize()?.strip_prefix(workdir)?.to_path_buf())
     }
 
     pub fn get_name(&self, remote_name: Option<&str>) -> Result<String> {
@@ -91,6 +100,116 @@ impl Repo  {
         }
     }
 
+    /// Resolves a revision like `HEAD`, `HEAD~2` or a short sha into a full commit sha.
+    pub fn resolve_commit(&self, rev: &str) -> Result<String> {
+        let commit = self.repo.revparse_single(rev)?.peel_to_commit()?;
+
+        Ok(commit.id().to_string())
+    }
+
+    /// The commit of the local branch's upstream, the current branch by default.
+    fn upstream_commit(&self, branch_name: Option<&str>) -> Result<git2::Commit<'_>> {
+        let reference = match branch_name {
+            Some(branch_name) => self.repo.find_branch(branch_name, git2::BranchType::Local)?.into_reference(),
+            None => self.repo.head()?,
+        };
+        let refname = reference.name().context("unable to get a branch name due to non-utf8 symbols")?;
+        let upstream_name = self.repo.branch_upstream_name(refname)?;
+
+        let commit = self.repo
+            .find_reference(upstream_name.as_str().context("unable to get an upstream name due to non-utf8 symbols")?)?
+            .peel_to_commit()?;
+
+        Ok(commit)
+    }
+
+    /// The pushed head of the local branch, the current branch by default.
+    pub fn upstream_revision(&self, branch_name: Option<&str>) -> Result<String> {
+        Ok(self.upstream_commit(branch_name)?.id().to_string())
+    }
+
+    /// Whether the commit is already in the upstream of the current branch.
+    pub fn is_pushed(&self, rev: &str) -> Result<bool> {
+        let oid = self.repo.revparse_single(rev)?.peel_to_commit()?.id();
+        let upstream_oid = self.upstream_commit(None)?.id();
+
+        Ok(upstream_oid == oid || self.repo.graph_descendant_of(upstream_oid, oid)?)
+    }
+
+    /// The upstream commit of the current branch and a patch of everything on top of it:
+    /// local commits, staged, unstaged and untracked changes.
+    pub fn diff_against_upstream(&self) -> Result<(String, String)> {
+        let upstream = self.upstream_commit(None)?;
+
+        let mut options = git2::DiffOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+
+        let diff = self.repo.diff_tree_to_workdir_with_index(Some(&upstream.tree()?), Some(&mut options))?;
+        let mut patch = String::new();
+
+        diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+
+            true
+        })?;
+
+        Ok((upstream.id().to_string(), patch))
+    }
+
+    /// The head of the default branch of the current branch's upstream remote.
+    fn default_branch_commit(&self) -> Result<git2::Commit<'_>> {
+        let head = self.repo.head()?;
+        let refname = head.name().context("unable to get a branch name due to non-utf8 symbols")?;
+        let upstream_name = self.repo.branch_upstream_name(refname).context("The current branch has no upstream")?;
+        let remote = self.repo.branch_remote_name(upstream_name.as_str().context("unable to get an upstream name due to non-utf8 symbols")?)?;
+        let remote = remote.as_str().context("unable to get a remote name due to non-utf8 symbols")?;
+
+        let reference = self
+            .repo
+            .find_reference(&format!("refs/remotes/{remote}/HEAD"))
+            .with_context(|| format!("Cannot find the default branch of {remote}, set it with `git remote set-head {remote} --auto`"))?;
+
+        Ok(reference.peel_to_commit()?)
+    }
+
+    /// Paths of the files changed since the commit forked from the default branch,
+    /// up to the revision or up to the working tree (including untracked files) when it isn't given.
+    pub fn changed_paths(&self, rev: Option<&str>) -> Result<Vec<PathBuf>> {
+        let head = self.repo.revparse_single(rev.unwrap_or("HEAD"))?.peel_to_commit()?;
+        let base = self.repo.merge_base(head.id(), self.default_branch_commit()?.id())?;
+        let base = self.repo.find_commit(base)?.tree()?;
+
+        let diff = match rev {
+            Some(_) => self.repo.diff_tree_to_tree(Some(&base), Some(&head.tree()?), None)?,
+            None => {
+                let mut options = git2::DiffOptions::new();
+                options.include_untracked(true).recurse_untracked_dirs(true);
+
+                self.repo.diff_tree_to_workdir_with_index(Some(&base), Some(&mut options))?
+            }
+        };
+
+        // both sides, so that a file moved out of a directory counts for it too
+        let mut paths: Vec<PathBuf> = diff
+            .deltas()
+            .flat_map(|delta| [delta.old_file().path(), delta.new_file().path()])
+            .flatten()
+            .map(Path::to_path_buf)
+            .collect();
+
+        paths.sort();
+        paths.dedup();
+
+        Ok(paths)
+    }
+
     pub fn count_ahead_commits(&self) -> Result<usize> {
         let mut revwalk = self.repo.revwalk()?;
         // TODO: get default branch name
@@ -323,4 +442,88 @@ mod tests {
 
         assert!(repo.get_branch_name_meta(Some("non-existed")).is_err());
     }
+
+    #[test]
+    fn resolve_commit_test() {
+        let (path, repo) = repo_init();
+        let head = repo.head().unwrap().peel_to_commit().unwrap().id().to_string();
+        drop(repo);
+
+        let repo = Repo::new(Some(path.path())).unwrap();
+        assert_eq!(repo.resolve_commit("HEAD").unwrap(), head);
+        assert_eq!(repo.resolve_commit(&head[..7]).unwrap(), head);
+        assert!(repo.resolve_commit("non-existed").is_err());
+    }
+
+    #[test]
+    fn is_pushed_test() {
+        let (path, git) = repo_init();
+        git.remote("origin", "git@github.com:username/project.git").unwrap();
+        let head_id = git.head().unwrap().peel_to_commit().unwrap().id();
+
+        let repo = Repo::new(Some(path.path())).unwrap();
+        assert!(repo.is_pushed("HEAD").is_err());
+
+        repo.set_upstream("main", "main", head_id).unwrap();
+        assert!(repo.is_pushed("HEAD").unwrap());
+        assert_eq!(repo.upstream_revision(Some("main")).unwrap(), head_id.to_string());
+
+        let head = git.find_commit(head_id).unwrap();
+        let sig = git.signature().unwrap();
+        git.commit(Some("HEAD"), &sig, &sig, "local", &head.tree().unwrap(), &[&head]).unwrap();
+
+        assert!(!repo.is_pushed("HEAD").unwrap());
+        assert!(repo.is_pushed("HEAD~1").unwrap());
+    }
+
+    #[test]
+    fn diff_against_upstream_test() {
+        let (path, git) = repo_init();
+        git.remote("origin", "git@github.com:username/project.git").unwrap();
+        let head_id = git.head().unwrap().peel_to_commit().unwrap().id();
+
+        let repo = Repo::new(Some(path.path())).unwrap();
+        repo.set_upstream("main", "main", head_id).unwrap();
+
+        std::fs::write(path.path().join("new.txt"), "hello\n").unwrap();
+
+        let (base, patch) = repo.diff_against_upstream().unwrap();
+        assert_eq!(base, head_id.to_string());
+        assert!(patch.contains("+++ b/new.txt"));
+        assert!(patch.contains("+hello"));
+    }
+
+    #[test]
+    fn changed_paths_test() {
+        let (path, git) = repo_init();
+        git.remote("origin", "git@github.com:username/project.git").unwrap();
+        let head_id = git.head().unwrap().peel_to_commit().unwrap().id();
+
+        let repo = Repo::new(Some(path.path())).unwrap();
+        repo.set_upstream("main", "main", head_id).unwrap();
+        assert!(repo.changed_paths(Some("HEAD")).is_err());
+
+        git.reference_symbolic("refs/remotes/origin/HEAD", "refs/remotes/origin/main", true, "default branch").unwrap();
+
+        std::fs::create_dir(path.path().join("api")).unwrap();
+        std::fs::write(path.path().join("api/main.rs"), "fn main() {}\n").unwrap();
+
+        let mut index = git.index().unwrap();
+        index.add_path(std::path::Path::new("api/main.rs")).unwrap();
+        let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+        let head = git.find_commit(head_id).unwrap();
+        let sig = git.signature().unwrap();
+        git.commit(Some("HEAD"), &sig, &sig, "api", &tree, &[&head]).unwrap();
+
+        std::fs::write(path.path().join("web.ts"), "export {}\n").unwrap();
+
+        assert_eq!(repo.changed_paths(Some("HEAD")).unwrap(), vec![std::path::PathBuf::from("api/main.rs")]);
+        assert_eq!(
+            repo.changed_paths(None).unwrap(),
+            vec![std::path::PathBuf::from("api/main.rs"), std::path::PathBuf::from("web.ts")]
+        );
+
+        let nested = Repo::new(Some(&path.path().join("api"))).unwrap();
+        assert_eq!(nested.relative_path().unwrap(), std::path::PathBuf::from("api"));
+    }
 }
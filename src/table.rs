@@ -24,3 +24,33 @@ impl Table {
         table
     }
 }
+
+/// A text sparkline of the values scaled between their min and max, gaps are left blank.
+pub fn sparkline(values: &[Option<f64>]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let present = values.iter().flatten();
+    let min = present.clone().copied().fold(f64::INFINITY, f64::min);
+    let max = present.copied().fold(f64::NEG_INFINITY, f64::max);
+
+    values
+        .iter()
+        .map(|value| match value {
+            Some(_) if (max - min).abs() < f64::EPSILON => BARS[BARS.len() / 2],
+            Some(value) => BARS[(((value - min) / (max - min)) * (BARS.len() - 1) as f64).round() as usize],
+            None => ' ',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sparkline;
+
+    #[test]
+    fn sparkline_test() {
+        assert_eq!(sparkline(&[Some(1.0), Some(8.0), None, Some(4.5)]), "▁█ ▅");
+        assert_eq!(sparkline(&[Some(3.0), Some(3.0)]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+}
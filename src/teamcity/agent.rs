@@ -0,0 +1,205 @@
+use crate::teamcity::build::Properties;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Pool {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningBuild {
+    id: i32,
+    build_type_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildTypeRef {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnmetRequirements {
+    description: Option<String>,
+    #[serde(flatten)]
+    properties: Properties,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Compatibility {
+    build_type: Option<BuildTypeRef>,
+    unmet_requirements: Option<UnmetRequirements>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Compatibilities {
+    #[serde(default)]
+    compatibility: Vec<Compatibility>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Agent {
+    name: String,
+    connected: bool,
+    enabled: bool,
+    authorized: bool,
+    pool: Option<Pool>,
+    build: Option<RunningBuild>,
+    incompatible_build_types: Option<Compatibilities>,
+}
+
+impl Agent {
+    const FIELDS: &'static str = "nextHref,agent(name,connected,enabled,authorized,pool(name),build(id,buildTypeId))";
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pool(&self) -> &str {
+        self.pool.as_ref().map(|p| p.name.as_str()).unwrap_or_default()
+    }
+
+    /// connected/disconnected, disabled and unauthorized agents don't run builds
+    pub fn state(&self) -> &'static str {
+        match (self.connected, self.enabled, self.authorized) {
+            (_, _, false) => "unauthorized",
+            (_, false, _) => "disabled",
+            (false, _, _) => "disconnected",
+            (true, true, true) => "connected",
+        }
+    }
+
+    pub fn running_build(&self) -> Option<String> {
+        self.build
+            .as_ref()
+            .map(|b| format!("#{} {}", b.id, b.build_type_id))
+    }
+
+    /// Requirements of the build type the agent doesn't meet.
+    pub fn unmet_requirements(&self, build_type_id: &str) -> Vec<String> {
+        self.incompatible_build_types
+            .iter()
+            .flat_map(|c| c.compatibility.iter())
+            .filter(|c| c.build_type.as_ref().map(|bt| bt.id.as_str()) == Some(build_type_id))
+            .filter_map(|c| c.unmet_requirements.as_ref())
+            .flat_map(|unmet| {
+                let properties: Vec<String> = unmet
+                    .properties
+                    .property
+                    .iter()
+                    .map(|p| match p.value.as_str() {
+                        "" => p.name.clone(),
+                        value => format!("{} {}", p.name, value),
+                    })
+                    .collect();
+
+                match (properties.is_empty(), &unmet.description) {
+                    (true, Some(description)) => vec![description.clone()],
+                    _ => properties,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Agents {
+    next_href: Option<String>,
+    #[serde(default)]
+    agent: Vec<Agent>,
+}
+
+impl Paginated for Agents {
+    type Item = Agent;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Agent> {
+        self.agent
+    }
+}
+
+/// Agents able to run the build type, and the other ones with the requirements they fail.
+pub struct AgentCompatibility {
+    pub compatible: Vec<Agent>,
+    pub incompatible: Vec<Agent>,
+}
+
+impl<'a> Client<'a> {
+    async fn agents(&self, locator: &str, fields: &str) -> Result<Vec<Agent>> {
+        let url = format!(
+            "/app/rest/agents?locator={locator},defaultFilter:false,count:{count}&fields={fields}",
+            count = Limit::All.page_size()
+        );
+
+        self.collect::<Agents>(url, Limit::All).await
+    }
+
+    /// All agents, including disconnected and unauthorized ones.
+    pub async fn get_agents(&self) -> Result<Vec<Agent>> {
+        self.agents("enabled:any", Agent::FIELDS).await
+    }
+
+    pub async fn get_agent_compatibility(&self, build_type_id: Option<&str>) -> Result<AgentCompatibility> {
+        let build_type_id = build_type_id
+            .or(self.build_type)
+            .context("No build type for current repo")?;
+
+        let incompatible_fields = Agent::FIELDS.replace(
+            "build(id,buildTypeId)",
+            "build(id,buildTypeId),incompatibleBuildTypes(compatibility(buildType(id),unmetRequirements(description,property(name,value))))",
+        );
+
+        let compatible_locator = format!("compatible:(buildType:(id:{build_type_id}))");
+        let incompatible_locator = format!("incompatible:(buildType:(id:{build_type_id}))");
+
+        let (compatible, incompatible) = futures::try_join!(
+            self.agents(&compatible_locator, Agent::FIELDS),
+            self.agents(&incompatible_locator, &incompatible_fields),
+        )?;
+
+        Ok(AgentCompatibility { compatible, incompatible })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Agent;
+
+    #[test]
+    fn unmet_requirements_test() {
+        let agent: Agent = serde_json::from_value(serde_json::json!({
+            "name": "agent-1",
+            "connected": true,
+            "enabled": false,
+            "authorized": true,
+            "incompatibleBuildTypes": { "compatibility": [
+                {
+                    "buildType": { "id": "App_Build" },
+                    "unmetRequirements": {
+                        "description": "Unmet requirements",
+                        "property": [{ "name": "docker.server.version", "value": "" }, { "name": "env.JDK", "value": "17" }]
+                    }
+                },
+                {
+                    "buildType": { "id": "Other_Build" },
+                    "unmetRequirements": { "description": "Agent is disabled" }
+                }
+            ] }
+        }))
+        .unwrap();
+
+        assert_eq!(agent.state(), "disabled");
+        assert_eq!(agent.unmet_requirements("App_Build"), vec!["docker.server.version", "env.JDK 17"]);
+        assert_eq!(agent.unmet_requirements("Other_Build"), vec!["Agent is disabled"]);
+        assert!(agent.unmet_requirements("Unknown").is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    name: String,
+    full_name: String,
+    size: Option<u64>,
+    modification_time: Option<String>,
+}
+
+impl Artifact {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path relative to the artifacts root, files inside archives look like `archive.zip!/path`.
+    pub fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.size.is_none()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.full_name.matches('/').count()
+    }
+
+    pub fn modified_at(&self) -> String {
+        crate::teamcity::build::format_date(self.modification_time.as_deref())
+    }
+
+    /// Where to save the artifact inside `dir`, keeping its relative path.
+    /// Files inside `archive.zip` go to `archive.zip.d`, so they don't clash with the archive itself.
+    pub fn destination(&self, dir: &Path) -> Result<PathBuf> {
+        let relative = PathBuf::from(self.full_name.replace("!/", ".d/"));
+
+        // the name comes from the server, it must not point outside of `dir`
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            anyhow::bail!("Refusing to save artifact {} outside of {}", self.full_name, dir.display());
+        }
+
+        Ok(dir.join(relative))
+    }
+
+    /// The full name with percent-encoded path segments, keeping the `!/` archive separators.
+    fn url_path(&self) -> String {
+        self.full_name
+            .split("!/")
+            .map(|part| {
+                part.split('/')
+                    // spaces are serialized as `+`, which is a literal plus in a path
+                    .map(|segment| url::form_urlencoded::byte_serialize(segment.as_bytes()).collect::<String>().replace('+', "%20"))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect::<Vec<_>>()
+            .join("!/")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Artifacts {
+    #[serde(default)]
+    file: Vec<Artifact>,
+}
+
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut value = size as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{size} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+impl<'a> Client<'a> {
+    /// All artifacts of the build, including the files inside archives when `browse_archives` is set.
+    pub async fn get_artifacts(&self, build_id: i32, browse_archives: bool) -> Result<Vec<Artifact>> {
+        let url = format!(
+            "/app/rest/builds/id:{build_id}/artifacts/children/?locator=recursive:true,browseArchives:{browse_archives}&fields=file(name,fullName,size,modificationTime)"
+        );
+
+        let mut artifacts: Artifacts = self.http_client.get(url).await?;
+        artifacts.file.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+
+        Ok(artifacts.file)
+    }
+
+    /// Files (including ones inside archives) matching the glob.
+    pub async fn find_artifacts(&self, build_id: i32, glob: &str) -> Result<Vec<Artifact>> {
+        let pattern = glob::Pattern::new(glob)?;
+        let browse_archives = glob.contains("!/");
+
+        Ok(self
+            .get_artifacts(build_id, browse_archives)
+            .await?
+            .into_iter()
+            .filter(|a| !a.is_dir() && pattern.matches(a.full_name()))
+            .collect())
+    }
+
+    /// Downloads the artifact into `dir`, files inside archives are fetched one by one.
+    pub async fn download_artifact<F>(&self, build_id: i32, artifact: &Artifact, dir: &Path, on_chunk: F) -> Result<PathBuf>
+    where
+        F: FnMut(u64),
+    {
+        let destination = artifact.destination(dir)?;
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // a failed download shouldn't leave a truncated file behind
+        let mut partial = destination.clone().into_os_string();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+
+        let mut file = std::fs::File::create(&partial)
+            .with_context(|| format!("Cannot create {}", partial.display()))?;
+
+        let url = format!("/app/rest/builds/id:{build_id}/artifacts/files/{}", artifact.url_path());
+
+        if let Err(err) = self.http_client.download(url, &mut file, on_chunk).await {
+            drop(file);
+            let _ = std::fs::remove_file(&partial);
+
+            return Err(err);
+        }
+
+        std::fs::rename(&partial, &destination)
+            .with_context(|| format!("Cannot move the download to {}", destination.display()))?;
+
+        Ok(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_size, Artifact};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn format_size_test() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 + 512 * 1024), "5.5 MB");
+    }
+
+    #[test]
+    fn destination_test() {
+        let artifact = Artifact {
+            name: "report.html".into(),
+            full_name: "reports.zip!/html/report.html".into(),
+            size: Some(1),
+            modification_time: None,
+        };
+
+        assert_eq!(artifact.depth(), 2);
+        assert_eq!(
+            artifact.destination(Path::new("out")).unwrap(),
+            PathBuf::from("out/reports.zip.d/html/report.html")
+        );
+
+        let escaping = Artifact {
+            full_name: "reports.zip!/../../.bashrc".into(),
+            ..artifact
+        };
+
+        assert!(escaping.destination(Path::new("out")).is_err());
+    }
+
+    #[test]
+    fn url_path_test() {
+        let artifact = Artifact {
+            name: "a #1?.txt".into(),
+            full_name: "my reports.zip!/100%/a #1?.txt".into(),
+            size: Some(1),
+            modification_time: None,
+        };
+
+        assert_eq!(artifact.url_path(), "my%20reports.zip!/100%25/a%20%231%3F.txt");
+    }
+}
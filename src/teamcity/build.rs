@@ -1,26 +1,232 @@
-use crate::teamcity::build_locator::BuildLocatorBuilder;
+use crate::teamcity::build_locator::{BuildLocatorBuilder, BuildSelector, BuildState, BuildStatus};
 use crate::teamcity::build_type_locator::BuildTypeLocator;
+use crate::teamcity::paging::{Limit, Paginated};
 use crate::teamcity::Client;
 use crate::normalize::{normalize_field_names, select_many};
 use crate::teamcity::user::{Triggered, User};
 use crate::teamcity::{ArgBuildType, BuildQueue};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use skim::prelude::*;
 use struct_field_names_as_array::FieldNamesAsArray;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
 struct BuildTypeBody<'a> {
     id: &'a str,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+}
+
+/// Parses `name=value` pairs given on the command line.
+impl std::str::FromStr for Property {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, value) = s
+            .split_once('=')
+            .context("Expected a parameter in the name=value format")?;
+
+        Ok(Self {
+            name: name.trim().to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Properties {
+    #[serde(default)]
+    pub property: Vec<Property>,
+}
+
+impl From<Vec<Property>> for Properties {
+    fn from(property: Vec<Property>) -> Self {
+        Self { property }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VcsRootInstance {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RevisionChange {
+    vcs_root_instance: Option<VcsRootInstance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevisionChanges {
+    #[serde(default)]
+    change: Vec<RevisionChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VcsRootInstances {
+    #[serde(rename = "vcs-root-instance", default)]
+    vcs_root_instance: Vec<VcsRootInstance>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vcs_branch_name: Option<String>,
+    #[serde(rename = "vcs-root-instance", skip_serializing_if = "Option::is_none")]
+    vcs_root_instance: Option<VcsRootInstance>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Revisions {
+    #[serde(default)]
+    revision: Vec<Revision>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tag {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Tags {
+    #[serde(default)]
+    tag: Vec<Tag>,
+}
+
+impl Tags {
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tag.iter().map(|t| t.name.as_str())
+    }
+}
+
+impl<S: AsRef<str>> From<&[S]> for Tags {
+    fn from(names: &[S]) -> Self {
+        Self {
+            tag: names.iter().map(|name| Tag { name: name.as_ref().to_string() }).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
+struct CommentBody<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct PinInfoBody<'a> {
+    status: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<CommentBody<'a>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BuildRef {
+    pub id: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BuildRefs {
+    #[serde(default)]
+    build: Vec<BuildRef>,
+}
+
+impl BuildRefs {
+    pub fn ids(&self) -> impl Iterator<Item = i32> + '_ {
+        self.build.iter().map(|b| b.id)
+    }
+}
+
+impl From<Vec<i32>> for BuildRefs {
+    fn from(ids: Vec<i32>) -> Self {
+        Self {
+            build: ids.into_iter().map(|id| BuildRef { id }).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 struct BuildBody<'a> {
-    branch_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch_name: Option<&'a str>,
     build_type: BuildTypeBody<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revisions: Option<Revisions>,
+    #[serde(rename = "snapshot-dependencies", skip_serializing_if = "Option::is_none")]
+    snapshot_dependencies: Option<BuildRefs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<Properties>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    personal: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_changes: Option<LastChanges>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Tags>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<CommentBody<'a>>,
 }
 
-#[derive(Debug, Deserialize, FieldNamesAsArray)]
+#[derive(Debug, Serialize)]
+struct PersonalChange {
+    id: i32,
+    personal: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct LastChanges {
+    change: Vec<PersonalChange>,
+}
+
+/// What to queue with `run_build`, the repo's build type and current branch by default.
+#[derive(Debug, Default)]
+pub struct RunBuildOptions<'a> {
+    pub build_type: Option<&'a str>,
+    pub branch_name: Option<&'a str>,
+    /// pin the build to the commit
+    pub revision: Option<&'a str>,
+    pub properties: Vec<Property>,
+    /// ask for the build type's parameters
+    pub interactive: bool,
+    /// wait for TeamCity to detect the revision, no VCS check when `None`
+    pub vcs_timeout: Option<std::time::Duration>,
+    /// use successful builds of the same revision as direct snapshot dependencies
+    pub reuse_dependencies: bool,
+    /// queue a personal build with the patch applied on top of the revision
+    pub personal_patch: Option<String>,
+    pub tags: Vec<String>,
+    pub comment: Option<&'a str>,
+}
+
+/// Everything needed to queue an identical build.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildDefinition {
+    build_type_id: String,
+    branch_name: Option<String>,
+    revisions: Option<Revisions>,
+    #[serde(rename = "snapshot-dependencies")]
+    snapshot_dependencies: Option<BuildRefs>,
+    properties: Option<Properties>,
+}
+
+impl BuildDefinition {
+    const FIELDS: &'static str = "buildTypeId,branchName,revisions(revision(version,vcsBranchName,vcs-root-instance(id))),snapshot-dependencies(build(id)),properties(property(name,value))";
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildCancelRequest<'a> {
+    comment: Option<&'a str>,
+    readd_into_queue: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, FieldNamesAsArray)]
 #[serde(rename_all = "camelCase")]
 #[field_names_as_array(rename_all = "camelCase")]
 pub struct Build {
@@ -32,6 +238,8 @@ pub struct Build {
     web_url: String,
     finish_on_agent_date: Option<String>,
     triggered: Triggered,
+    pinned: Option<bool>,
+    tags: Option<Tags>,
 }
 
 fn format_datetime(datetime: &chrono::DateTime<chrono::FixedOffset>) -> String {
@@ -55,6 +263,18 @@ fn format_datetime(datetime: &chrono::DateTime<chrono::FixedOffset>) -> String {
     }
 }
 
+/// Parses a timestamp in TeamCity's format, e.g. `20230101T120000+0000`.
+pub(crate) fn parse_timestamp(date: Option<&str>) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    date.and_then(|str| chrono::DateTime::parse_from_str(str, "%Y%m%dT%H%M%S%z").ok())
+}
+
+/// Formats a date in TeamCity's format (e.g. `20230101T120000+0000`) relatively to now.
+pub(crate) fn format_date(date: Option<&str>) -> String {
+    parse_timestamp(date)
+        .map(|date| format_datetime(&date))
+        .unwrap_or_default()
+}
+
 impl Build {
     pub fn status(&self) -> Option<&str> {
         self.status.as_deref()
@@ -65,11 +285,7 @@ impl Build {
     }
 
     pub fn finished_at(&self) -> String {
-        self.finish_on_agent_date
-            .as_ref()
-            .and_then(|str| chrono::DateTime::parse_from_str(str, "%Y%m%dT%H%M%S%z").ok())
-            .map(|date| format_datetime(&date))
-            .unwrap_or_default()
+        format_date(self.finish_on_agent_date.as_deref())
     }
 
     pub fn build_type_id(&self) -> &str {
@@ -85,11 +301,31 @@ impl Build {
     }
 
     pub fn triggered_by(&self) -> &str {
-        if let Some(user) = &self.triggered.user {
-            return user.name.as_str();
-        } else {
-            return self.triggered.r#type.as_str();
-        }
+        self.triggered.by()
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.unwrap_or_default()
+    }
+
+    pub fn tags(&self) -> Vec<&str> {
+        self.tags.as_ref().map(|t| t.names().collect()).unwrap_or_default()
+    }
+}
+
+impl SkimItem for Build {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Owned(format!(
+            "#{} {} {} {}",
+            self.id,
+            self.build_type_id,
+            self.branch_name().unwrap_or_default(),
+            self.state
+        ))
+    }
+
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::Text(format!("{self:#?}"))
     }
 }
 
@@ -97,28 +333,44 @@ impl Build {
 #[serde(rename_all = "camelCase")]
 #[field_names_as_array(rename_all = "camelCase")]
 pub struct Builds {
+    next_href: Option<String>,
+    #[serde(default)]
     build: Vec<Build>,
 }
 
+impl Paginated for Builds {
+    type Item = Build;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Build> {
+        self.build
+    }
+}
+
 impl Builds {
     pub fn fields() -> String {
         normalize_field_names(&Builds::FIELD_NAMES_AS_ARRAY).replace(
             "build",
             &format!(
                 "build({})",
-                normalize_field_names(&Build::FIELD_NAMES_AS_ARRAY).replace(
-                    "triggered",
-                    &format!(
-                        "triggered({})",
-                        normalize_field_names(&Triggered::FIELD_NAMES_AS_ARRAY).replace(
-                            "user",
-                            &format!(
-                                "user({})",
-                                normalize_field_names(&User::FIELD_NAMES_AS_ARRAY)
+                normalize_field_names(&Build::FIELD_NAMES_AS_ARRAY)
+                    .replace("tags", "tags(tag(name))")
+                    .replace(
+                        "triggered",
+                        &format!(
+                            "triggered({})",
+                            normalize_field_names(&Triggered::FIELD_NAMES_AS_ARRAY).replace(
+                                "user",
+                                &format!(
+                                    "user({})",
+                                    normalize_field_names(&User::FIELD_NAMES_AS_ARRAY)
+                                )
                             )
                         )
                     )
-                )
             ),
         )
     }
@@ -142,42 +394,284 @@ impl<'a> IntoIterator for &'a Builds {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningInfo {
+    percentage_complete: Option<u8>,
+    elapsed_seconds: Option<i64>,
+    estimated_total_seconds: Option<i64>,
+    current_stage_text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildProgress {
+    pub(crate) id: i32,
+    number: Option<String>,
+    build_type_id: String,
+    state: String,          // queued/running/finished
+    status: Option<String>, // SUCCESS/FAILURE/UNKNOWN
+    status_text: Option<String>,
+    web_url: String,
+    #[serde(rename = "running-info")]
+    running_info: Option<RunningInfo>,
+}
+
+impl BuildProgress {
+    const FIELDS: &'static str = "id,number,buildTypeId,state,status,statusText,webUrl,running-info(percentageComplete,elapsedSeconds,estimatedTotalSeconds,currentStageText)";
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub fn status_text(&self) -> Option<&str> {
+        self.status_text.as_deref()
+    }
+
+    pub fn web_url(&self) -> &str {
+        &self.web_url
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == "finished"
+    }
+
+    pub fn is_successful(&self) -> bool {
+        self.status.as_deref() == Some("SUCCESS")
+    }
+
+    pub fn percentage(&self) -> Option<u8> {
+        self.running_info.as_ref().and_then(|i| i.percentage_complete)
+    }
+
+    pub fn stage(&self) -> Option<&str> {
+        self.running_info.as_ref().and_then(|i| i.current_stage_text.as_deref())
+    }
+
+    /// Remaining time according to TeamCity's estimate, in seconds.
+    pub fn eta(&self) -> Option<i64> {
+        self.running_info.as_ref().and_then(|i| {
+            i.estimated_total_seconds
+                .zip(i.elapsed_seconds)
+                .map(|(total, elapsed)| (total - elapsed).max(0))
+        })
+    }
+
+    pub fn title(&self) -> String {
+        match &self.number {
+            Some(number) => format!("{} #{}", self.build_type_id, number),
+            None => format!("{} (id: {})", self.build_type_id, self.id),
+        }
+    }
+}
+
+pub(crate) const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
 impl<'a> Client<'a> {
-    pub async fn run_build(
-        &self,
-        build_type: Option<&str>,
-        branch_name: Option<&str>,
-    ) -> Result<BuildQueue> {
-        let build_type = build_type
-            .or_else(|| self.build_type)
+    pub async fn get_build_progress(&self, id: i32) -> Result<BuildProgress> {
+        let url = format!("/app/rest/builds/id:{id}?fields={fields}", fields = BuildProgress::FIELDS);
+
+        self.http_client.get(url).await
+    }
+
+    /// Polls the build until it is finished, calling `on_update` after each request.
+    pub async fn watch_build<F>(&self, id: i32, mut on_update: F) -> Result<BuildProgress>
+    where
+        F: FnMut(&BuildProgress),
+    {
+        loop {
+            let progress = self.get_build_progress(id).await?;
+
+            on_update(&progress);
+
+            if progress.is_finished() {
+                return Ok(progress);
+            }
+
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+
+    pub async fn run_build(&self, options: RunBuildOptions<'_>) -> Result<BuildQueue> {
+        let build_type = options.build_type
+            .or(self.build_type)
             .unwrap();
         // .context("Current path doesn't have association with BuildType through config (or contains non-utf8 symbols)")
 
-        let branch = self.repo.normalize_branch_name(branch_name)?;
+        let branch = self.repo.normalize_branch_name(options.branch_name)?;
+
+        // the pushed head of the branch, when the revision isn't pinned
+        let expected = options.revision
+            .map(ToOwned::to_owned)
+            .or_else(|| self.repo.upstream_revision(options.branch_name).ok());
+
+        if let Some(timeout) = options.vcs_timeout {
+            self.check_for_changes(build_type, expected.as_deref(), timeout).await?;
+        }
+
+        let properties = if options.interactive {
+            self.prompt_parameters(build_type, options.properties).await?
+        } else {
+            options.properties
+        };
+
+        let revisions = match options.revision {
+            Some(version) => Some(self.pinned_revisions(build_type, &branch, version).await?),
+            None => None,
+        };
+
+        let snapshot_dependencies = match expected.as_deref() {
+            Some(revision) if options.reuse_dependencies => {
+                Some(self.find_reusable_dependencies(build_type, &branch, revision).await?)
+            }
+            None if options.reuse_dependencies => {
+                eprintln!(
+                    "{}",
+                    console::style(format!("⚠ {branch} has no pushed revision, no dependency builds will be reused")).yellow()
+                );
+                None
+            }
+            _ => None,
+        };
+
+        let last_changes = match options.personal_patch {
+            Some(patch) => {
+                let description = format!("Personal build of {branch}");
+                let id = self.upload_personal_change(patch, &description).await?;
+
+                Some(LastChanges {
+                    change: vec![PersonalChange { id, personal: true }],
+                })
+            }
+            None => None,
+        };
 
         let body = BuildBody {
             build_type: BuildTypeBody { id: build_type },
-            branch_name: &branch,
+            branch_name: Some(&branch),
+            revisions,
+            snapshot_dependencies: snapshot_dependencies.filter(|deps| !deps.build.is_empty()),
+            properties: (!properties.is_empty()).then(|| properties.into()),
+            personal: last_changes.is_some().then_some(true),
+            last_changes,
+            tags: (!options.tags.is_empty()).then(|| Tags::from(options.tags.as_slice())),
+            comment: options.comment.map(|text| CommentBody { text }),
+        };
+
+        self.http_client.post("/app/rest/buildQueue", &body).await
+    }
+
+    /// Revisions pinning the build to the commit of the build type's VCS root which contains it.
+    async fn pinned_revisions(&self, build_type: &str, branch: &str, version: &str) -> Result<Revisions> {
+        let url = format!("/app/rest/vcs-root-instances?locator=buildType:(id:{build_type})&fields=vcs-root-instance(id)");
+        let instances: VcsRootInstances = self.http_client.get(url).await?;
+
+        let vcs_root_instance = match instances.vcs_root_instance.len() {
+            0 | 1 => instances.vcs_root_instance.into_iter().next(),
+            _ => Some(self.revision_root_instance(build_type, version).await?),
+        };
+
+        Ok(Revisions {
+            revision: vec![Revision {
+                version: version.to_string(),
+                vcs_branch_name: Some(format!("refs/heads/{branch}")),
+                vcs_root_instance,
+            }],
+        })
+    }
+
+    /// The only VCS root instance of the build type in which TeamCity has detected the commit.
+    async fn revision_root_instance(&self, build_type: &str, version: &str) -> Result<VcsRootInstance> {
+        let url = format!(
+            "/app/rest/changes?locator=buildType:(id:{build_type}),version:{version},branch:(default:any)&fields=change(vcsRootInstance(id))"
+        );
+        let changes: RevisionChanges = self.http_client.get(url).await?;
+
+        let mut instances: Vec<VcsRootInstance> = Vec::new();
+
+        for instance in changes.change.into_iter().filter_map(|c| c.vcs_root_instance) {
+            if !instances.contains(&instance) {
+                instances.push(instance);
+            }
+        }
+
+        match instances.len() {
+            1 => Ok(instances.remove(0)),
+            0 => anyhow::bail!("{build_type} has several VCS roots and none of them contains {version}, make sure it is pushed"),
+            _ => anyhow::bail!("{version} is found in several VCS roots of {build_type}, cannot tell which one to pin"),
+        }
+    }
+
+    /// Queues a build with the same build type, branch, revisions, snapshot dependencies
+    /// and custom parameters as the given one.
+    pub async fn rerun_build(&self, id: i32) -> Result<BuildQueue> {
+        let url = format!("/app/rest/builds/id:{id}?fields={fields}", fields = BuildDefinition::FIELDS);
+        let definition: BuildDefinition = self.http_client.get(url).await?;
+
+        let body = BuildBody {
+            build_type: BuildTypeBody { id: &definition.build_type_id },
+            branch_name: definition.branch_name.as_deref(),
+            revisions: definition.revisions,
+            snapshot_dependencies: definition.snapshot_dependencies,
+            properties: definition.properties,
+            ..BuildBody::default()
         };
 
         self.http_client.post("/app/rest/buildQueue", &body).await
     }
 
+    /// The last successful build of the repo's build type on the given branch.
+    pub async fn last_green_build_id(&self, branch: &str) -> Result<Option<i32>> {
+        let selector = BuildSelector::LastGreen(branch.into());
+        let locator = selector.locator(self.build_type)?;
+
+        let url = format!("/app/rest/builds?locator={locator}&fields=build(id)");
+        let builds: BuildRefs = self.http_client.get(url).await?;
+
+        Ok(builds.build.first().map(|b| b.id))
+    }
+
+    /// The last failed build of the repo's build type on the given branch.
+    pub async fn get_last_failed_build_id(&self, branch_name: Option<&str>) -> Result<i32> {
+        let branch = self.repo.normalize_branch_name(branch_name)?;
+
+        let locator = BuildLocatorBuilder::default()
+            .build_type(self.build_type)
+            .branch(Some(branch))
+            .status(Some(BuildStatus::Failure))
+            .personal(Some(false))
+            .count(Some(1))
+            .build()?;
+
+        let url = format!("/app/rest/builds?locator={locator}&fields=build(id)");
+        let builds: BuildRefs = self.http_client.get(url).await?;
+
+        builds
+            .build
+            .first()
+            .map(|b| b.id)
+            .context("No failed builds found")
+    }
+
     pub async fn get_builds(
         &self,
         branch_name: Option<&str>,
         build_type: Option<&ArgBuildType>,
         author: Option<&str>,
-        limit: Option<u8>,
-    ) -> Result<Builds> {
+        mut filter: BuildLocatorBuilder<'_>,
+        limit: Limit,
+    ) -> Result<Vec<Build>> {
         let branch = self.repo.normalize_branch_name(branch_name)?;
 
-        let locator = BuildLocatorBuilder::default()
-            .count(limit)
+        let locator = filter
+            .count(Some(limit.page_size()))
             .user(author)
             .branch(Some(branch))
             .default_filter(Some(false))
-            .personal(Some(false))
             .build_type(
                 match build_type
                     .cloned()
@@ -189,7 +683,7 @@ impl<'a> Client<'a> {
                     ArgBuildType::Custom(custom) => {
                         self.build_type_list()
                             .await
-                            .and_then(|list| select_many(list.build_type, Some(&custom)))
+                            .and_then(|list| select_many(list, Some(&custom)))
                             .map(BuildTypeLocator::from)
                             .ok()
                     }
@@ -202,6 +696,91 @@ impl<'a> Client<'a> {
 
         let url = format!("/app/rest/builds?locator={locator}&fields={fields}");
 
-        self.http_client.get(url).await
+        self.collect::<Builds>(url, limit).await
+    }
+
+    /// Pins or unpins the build, so it isn't cleaned up.
+    pub async fn pin_build(&self, id: i32, pin: bool, comment: Option<&str>) -> Result<()> {
+        let body = PinInfoBody {
+            status: pin,
+            comment: comment.map(|text| CommentBody { text }),
+        };
+
+        self.http_client
+            .put::<_, serde_json::Value, _>(format!("/app/rest/builds/id:{id}/pinInfo"), &body)
+            .await
+            .map(|_| ())
+    }
+
+    /// Adds the tags to the build's ones.
+    pub async fn tag_build(&self, id: i32, tags: &[String]) -> Result<()> {
+        self.http_client
+            .post::<_, Tags, _>(format!("/app/rest/builds/id:{id}/tags"), &Tags::from(tags))
+            .await
+            .map(|_| ())
+    }
+
+    /// Removes the tags from the build, keeping the rest.
+    pub async fn untag_build(&self, id: i32, tags: &[String]) -> Result<()> {
+        let url = format!("/app/rest/builds/id:{id}/tags");
+        let current: Tags = self.http_client.get(&url).await?;
+
+        let remaining: Vec<&str> = current
+            .names()
+            .filter(|name| !tags.iter().any(|tag| tag == name))
+            .collect();
+
+        self.http_client
+            .put::<_, Tags, _>(url, &Tags::from(remaining.as_slice()))
+            .await
+            .map(|_| ())
+    }
+
+    /// Queued and running builds of the current user on the given branch.
+    pub async fn get_active_builds(&self, branch_name: Option<&str>) -> Result<Vec<Build>> {
+        let branch = self.repo.normalize_branch_name(branch_name)?;
+        let fields = Builds::fields();
+        let mut result = Vec::new();
+
+        for state in [BuildState::Queued, BuildState::Running] {
+            let locator = BuildLocatorBuilder::default()
+                .user(Some("current"))
+                .state(Some(state))
+                .branch(Some(branch.clone()))
+                .default_filter(Some(false))
+                .count(Some(Limit::All.page_size()))
+                .build()?;
+
+            let url = format!("/app/rest/builds?locator={locator}&fields={fields}");
+
+            result.extend(self.collect::<Builds>(url, Limit::All).await?);
+        }
+
+        Ok(result)
+    }
+
+    /// Cancels a running build or removes a queued one.
+    pub async fn cancel_build(&self, id: i32, comment: Option<&str>) -> Result<()> {
+        let build = self.get_build_progress(id).await?;
+
+        let body = BuildCancelRequest {
+            comment,
+            readd_into_queue: false,
+        };
+
+        match (build.state(), comment) {
+            ("queued", None) => self.http_client.delete(format!("/app/rest/buildQueue/id:{id}")).await,
+            ("queued", Some(_)) => self
+                .http_client
+                .post::<_, serde_json::Value, _>(format!("/app/rest/buildQueue/id:{id}"), &body)
+                .await
+                .map(|_| ()),
+            ("running", _) => self
+                .http_client
+                .post::<_, serde_json::Value, _>(format!("/app/rest/builds/id:{id}"), &body)
+                .await
+                .map(|_| ()),
+            (state, _) => anyhow::bail!("Build {} is {state} already", build.title()),
+        }
     }
 }
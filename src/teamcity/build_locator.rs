@@ -1,16 +1,104 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Default, Builder)]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum BuildState {
+    Queued,
+    Running,
+    Finished,
+    Canceled,
+    Any,
+}
+
+impl fmt::Display for BuildState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildState::Queued => write!(f, "state:queued"),
+            BuildState::Running => write!(f, "state:running"),
+            BuildState::Finished => write!(f, "state:finished"),
+            // canceled builds are finished ones with a special flag
+            BuildState::Canceled => write!(f, "state:finished,canceled:true"),
+            BuildState::Any => write!(f, "state:any"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum BuildStatus {
+    Success,
+    Failure,
+    Unknown,
+}
+
+impl fmt::Display for BuildStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildStatus::Success => write!(f, "SUCCESS"),
+            BuildStatus::Failure => write!(f, "FAILURE"),
+            BuildStatus::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Builder, Clone)]
 #[builder(default)]
 pub struct BuildLocator<'a> {
     id: Option<i32>,
     user: Option<&'a str>,
+    state: Option<BuildState>,
+    status: Option<BuildStatus>,
     #[builder(setter(custom))]
     build_type: Option<String>, // TODO: remove owning
-    count: Option<u8>,
+    count: Option<u32>,
     branch: Option<String>,
     personal: Option<bool>,
     default_filter: Option<bool>,
+    until_build: Option<i32>,
+    tag: Option<&'a str>,
+    pinned: Option<bool>,
+    since_date: Option<DateTime<FixedOffset>>,
+    until_date: Option<DateTime<FixedOffset>>,
+    revision: Option<&'a str>,
+    agent_name: Option<&'a str>,
+    failed_to_start: Option<bool>,
+}
+
+/// Formats a date for a locator; `+` of the timezone has to be escaped to survive in a query string.
+fn format_locator_date(date: &DateTime<FixedOffset>) -> String {
+    date.format("%Y%m%dT%H%M%S%z").to_string().replace('+', "%2B")
+}
+
+/// Parses a relative date like `30m`, `12h`, `2d` or `1w` (ago), or an absolute one like `2023-01-31`.
+pub fn parse_date(s: &str) -> Result<DateTime<FixedOffset>> {
+    let now = chrono::Local::now().fixed_offset();
+
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|dt| dt.and_local_timezone(*now.offset()).single())
+            .context("Invalid date");
+    }
+
+    let split = s.find(|c: char| !c.is_ascii_digit()).context("Expected a unit: m, h, d or w")?;
+    let (amount, unit) = s.split_at(split);
+    let amount: u64 = amount.parse().context("Expected a number, e.g. 2d")?;
+
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!("Unknown unit \"{unit}\", expected m, h, d or w"),
+    };
+
+    // the unit constructors of chrono::Duration panic on overflow
+    amount
+        .checked_mul(unit_secs)
+        .and_then(|secs| chrono::Duration::from_std(std::time::Duration::from_secs(secs)).ok())
+        .and_then(|duration| now.checked_sub_signed(duration))
+        .context("The date is too far in the past")
 }
 
 impl BuildLocatorBuilder<'_> {
@@ -41,11 +129,19 @@ impl<'a> fmt::Display for BuildLocator<'a> {
             locators.push(format!("user:{user}"));
         }
 
+        if let Some(state) = &self.state {
+            locators.push(state.to_string());
+        }
+
+        if let Some(status) = &self.status {
+            locators.push(format!("status:{status}"));
+        }
+
         if let Some(build_type) = &self.build_type {
             locators.push(format!("buildType:{build_type}"));
         }
 
-        if let Some(count) = &self.count.or(Some(5)) {
+        if let Some(count) = &self.count {
             locators.push(format!("count:{count}"));
         }
 
@@ -57,6 +153,164 @@ impl<'a> fmt::Display for BuildLocator<'a> {
             }
         }
 
+        if let Some(tag) = &self.tag {
+            locators.push(format!("tag:{tag}"));
+        }
+
+        if let Some(pinned) = &self.pinned {
+            locators.push(format!("pinned:{pinned}"));
+        }
+
+        if let Some(until_build) = &self.until_build {
+            locators.push(format!("untilBuild:(id:{until_build})"));
+        }
+
+        if let Some(since_date) = &self.since_date {
+            locators.push(format!("sinceDate:{}", format_locator_date(since_date)));
+        }
+
+        if let Some(until_date) = &self.until_date {
+            locators.push(format!("untilDate:{}", format_locator_date(until_date)));
+        }
+
+        if let Some(revision) = &self.revision {
+            locators.push(format!("revision:{revision}"));
+        }
+
+        if let Some(agent_name) = &self.agent_name {
+            locators.push(format!("agentName:{agent_name}"));
+        }
+
+        if let Some(failed_to_start) = &self.failed_to_start {
+            locators.push(format!("failedToStart:{failed_to_start}"));
+        }
+
         write!(f, "{}", locators.join(","))
     }
 }
+
+/// A build given by id or by one of the symbolic names:
+/// `master`, `last-green:<branch>`, `tag:<tag>` and `pinned`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildSelector {
+    Id(i32),
+    LastGreen(String),
+    Tag(String),
+    Pinned,
+}
+
+impl FromStr for BuildSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(id) = s.parse() {
+            return Ok(BuildSelector::Id(id));
+        }
+
+        match s.split_once(':') {
+            None if s == "master" => Ok(BuildSelector::LastGreen("master".into())),
+            None if s == "pinned" => Ok(BuildSelector::Pinned),
+            Some(("last-green", branch)) if !branch.is_empty() => Ok(BuildSelector::LastGreen(branch.into())),
+            Some(("tag", tag)) if !tag.is_empty() => Ok(BuildSelector::Tag(tag.into())),
+            _ => anyhow::bail!("Unknown build selector \"{s}\", expected an id, master, last-green:<branch>, tag:<tag> or pinned"),
+        }
+    }
+}
+
+impl BuildSelector {
+    /// The latest successfully finished build matching the selector.
+    pub fn locator<'a>(&'a self, build_type: Option<&'a str>) -> Result<BuildLocator<'a>> {
+        let mut builder = BuildLocatorBuilder::default();
+
+        if let BuildSelector::Id(id) = self {
+            return Ok(builder.id(Some(*id)).build()?);
+        }
+
+        builder
+            .build_type(build_type)
+            .status(Some(BuildStatus::Success))
+            .state(Some(BuildState::Finished))
+            .count(Some(1));
+
+        match self {
+            BuildSelector::LastGreen(branch) => builder.branch(Some(branch.clone())),
+            BuildSelector::Tag(tag) => builder.tag(Some(tag)).branch(Some("any".into())),
+            BuildSelector::Pinned => builder.pinned(Some(true)).branch(Some("any".into())),
+            BuildSelector::Id(_) => unreachable!(),
+        };
+
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_date, BuildLocatorBuilder, BuildSelector, BuildState, BuildStatus};
+
+    #[test]
+    fn build_selector_parse_test() {
+        assert_eq!("42".parse::<BuildSelector>().unwrap(), BuildSelector::Id(42));
+        assert_eq!("master".parse::<BuildSelector>().unwrap(), BuildSelector::LastGreen("master".into()));
+        assert_eq!("last-green:release/1.0".parse::<BuildSelector>().unwrap(), BuildSelector::LastGreen("release/1.0".into()));
+        assert_eq!("tag:rc".parse::<BuildSelector>().unwrap(), BuildSelector::Tag("rc".into()));
+        assert_eq!("pinned".parse::<BuildSelector>().unwrap(), BuildSelector::Pinned);
+        assert!("tag:".parse::<BuildSelector>().is_err());
+        assert!("unknown".parse::<BuildSelector>().is_err());
+    }
+
+    #[test]
+    fn build_selector_locator_test() {
+        let selector: BuildSelector = "master".parse().unwrap();
+
+        assert_eq!(
+            selector.locator(Some("Project_Build")).unwrap().to_string(),
+            "state:finished,status:SUCCESS,buildType:Project_Build,count:1,branch:master"
+        );
+
+        let selector: BuildSelector = "tag:rc".parse().unwrap();
+
+        assert_eq!(
+            selector.locator(None).unwrap().to_string(),
+            "state:finished,status:SUCCESS,count:1,branch:default:any,tag:rc"
+        );
+    }
+
+    #[test]
+    fn build_locator_filters_test() {
+        let since = chrono::DateTime::parse_from_rfc3339("2023-01-31T10:00:00+03:00").unwrap();
+
+        let locator = BuildLocatorBuilder::default()
+            .state(Some(BuildState::Canceled))
+            .status(Some(BuildStatus::Failure))
+            .since_date(Some(since))
+            .revision(Some("abc123"))
+            .agent_name(Some("agent-1"))
+            .failed_to_start(Some(true))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            locator.to_string(),
+            "state:finished,canceled:true,status:FAILURE,sinceDate:20230131T100000%2B0300,revision:abc123,agentName:agent-1,failedToStart:true"
+        );
+    }
+
+    #[test]
+    fn parse_date_test() {
+        let now = chrono::Local::now().fixed_offset();
+
+        let two_days_ago = parse_date("2d").unwrap();
+        assert!(((now - two_days_ago).num_seconds() - 2 * 24 * 3600).abs() < 5);
+
+        let hour_ago = parse_date("1h").unwrap();
+        assert!(((now - hour_ago).num_seconds() - 3600).abs() < 5);
+
+        let date = parse_date("2023-01-31").unwrap();
+        assert_eq!(date.format("%Y%m%dT%H%M%S").to_string(), "20230131T000000");
+
+        assert!(parse_date("2y").is_err());
+        assert!(parse_date("d").is_err());
+        assert!(parse_date("10").is_err());
+        assert!(parse_date("99999999999w").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::teamcity::build::WATCH_INTERVAL;
+use crate::teamcity::Client;
+use anyhow::Result;
+
+/// A single line of a TeamCity build log, e.g. `[10:15:03]E:\t\t [Step 1/2] error text`.
+#[derive(Debug, PartialEq)]
+pub struct LogLine<'a> {
+    /// ' ' for regular messages, 'W' for warnings, 'E' for errors, 'F' for failures, 'i' for info
+    status: char,
+    depth: usize,
+    text: &'a str,
+}
+
+impl<'a> LogLine<'a> {
+    pub fn text(&self) -> &str {
+        self.text
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self.status, 'E' | 'F')
+    }
+
+    fn parse(line: &'a str, prev: Option<&LogLine>) -> Self {
+        let header = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.get(8..))
+            .and_then(|rest| rest.strip_prefix(']'))
+            .and_then(|rest| {
+                let mut chars = rest.chars();
+                let status = chars.next()?;
+
+                chars.as_str().strip_prefix(':').map(|rest| (status, rest))
+            });
+
+        match header {
+            Some((status, rest)) => Self {
+                status,
+                depth: rest.chars().take_while(|c| *c == '\t').count(),
+                text: line,
+            },
+            // continuation of a multiline message (e.g. a stack trace) belongs to the previous line
+            None => Self {
+                status: prev.map(|p| p.status).unwrap_or(' '),
+                depth: prev.map(|p| p.depth + 1).unwrap_or(1),
+                text: line,
+            },
+        }
+    }
+}
+
+pub fn parse_log(log: &str) -> Vec<LogLine<'_>> {
+    let mut lines: Vec<LogLine> = Vec::new();
+
+    for line in log.lines().filter(|l| !l.trim().is_empty()) {
+        let parsed = LogLine::parse(line, lines.last());
+        lines.push(parsed);
+    }
+
+    lines
+}
+
+/// Keeps only the blocks which contain errors: the headers of failed blocks
+/// and the error messages themselves (with their nested lines).
+pub fn collapse_to_failed<'l, 'a>(lines: &'l [LogLine<'a>]) -> Vec<&'l LogLine<'a>> {
+    let mut result = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = &lines[i];
+        let subtree_len = lines[i + 1..]
+            .iter()
+            .take_while(|l| l.depth > line.depth)
+            .count();
+        let subtree = &lines[i + 1..i + 1 + subtree_len];
+
+        if line.is_error() {
+            result.push(line);
+            result.extend(subtree);
+        } else if subtree.iter().any(LogLine::is_error) {
+            result.push(line);
+            result.extend(collapse_to_failed(subtree));
+        }
+
+        i += 1 + subtree_len;
+    }
+
+    result
+}
+
+impl<'a> Client<'a> {
+    pub async fn get_build_log(&self, id: i32) -> Result<String> {
+        self.http_client
+            .get_text(format!("/downloadBuildLog.html?buildId={id}&plain=true"))
+            .await
+    }
+
+    /// Prints the lines appended to the log of a running build after the first `offset` bytes,
+    /// until the build is finished. Only the new part of the log is requested, and a line still
+    /// being written is held back until the next poll.
+    pub async fn follow_build_log<F>(&self, id: i32, mut offset: usize, mut on_line: F) -> Result<()>
+    where
+        F: FnMut(&str),
+    {
+        loop {
+            // check the state first, so the last fetched log is complete
+            let progress = self.get_build_progress(id).await?;
+            let appended = self
+                .http_client
+                .get_bytes_from(format!("/downloadBuildLog.html?buildId={id}&plain=true"), offset)
+                .await?;
+
+            let complete = match progress.is_finished() {
+                true => appended.len(),
+                false => appended.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1),
+            };
+
+            for line in String::from_utf8_lossy(&appended[..complete]).lines() {
+                on_line(line);
+            }
+
+            offset += complete;
+
+            if progress.is_finished() {
+                return Ok(());
+            }
+
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collapse_to_failed, parse_log};
+
+    const LOG: &str = "[10:00:00] :\t [Step 1/2] Step 1/2: Build
+[10:00:01] :\t\t [Step 1/2] compiling
+[10:00:02] :\t\t [Step 1/2] done
+[10:00:03] :\t [Step 2/2] Step 2/2: Test
+[10:00:04] :\t\t [Step 2/2] suite A
+[10:00:05] :\t\t\t [Step 2/2] test ok
+[10:00:06] :\t\t [Step 2/2] suite B
+[10:00:07]E:\t\t\t [Step 2/2] test failed
+java.lang.AssertionError
+\tat Foo.bar(Foo.java:1)
+[10:00:08] :\t\t [Step 2/2] suite C
+[10:00:09]E:\t [Step 2/2] Step Test failed";
+
+    #[test]
+    fn parse_log_test() {
+        let lines = parse_log(LOG);
+
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0].depth, 1);
+        assert_eq!(lines[5].depth, 3);
+        assert!(lines[7].is_error());
+        // continuation lines inherit the status of the message
+        assert!(lines[8].is_error());
+        assert_eq!(lines[8].depth, 4);
+    }
+
+    #[test]
+    fn collapse_to_failed_test() {
+        let lines = parse_log(LOG);
+        let collapsed: Vec<&str> = collapse_to_failed(&lines).iter().map(|l| l.text()).collect();
+
+        assert_eq!(
+            collapsed,
+            vec![
+                "[10:00:03] :\t [Step 2/2] Step 2/2: Test",
+                "[10:00:06] :\t\t [Step 2/2] suite B",
+                "[10:00:07]E:\t\t\t [Step 2/2] test failed",
+                "java.lang.AssertionError",
+                "\tat Foo.bar(Foo.java:1)",
+                "[10:00:09]E:\t [Step 2/2] Step Test failed",
+            ]
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use crate::teamcity::build::Properties;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::parameter::Parameters;
 use crate::teamcity::Client;
 use crate::normalize::*;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use skim::prelude::*;
 use std::convert::AsRef;
@@ -14,6 +17,172 @@ pub struct BuildType {
     name: String,
     web_url: String,
     r#type: Option<String>,
+    description: Option<String>,
+    project_name: Option<String>,
+    paused: Option<bool>,
+    // the settings below are requested by `get_build_type` only
+    #[field_names_as_array(skip)]
+    settings: Option<Properties>,
+    #[field_names_as_array(skip)]
+    parameters: Option<Parameters>,
+    #[field_names_as_array(skip)]
+    #[serde(rename = "vcs-root-entries")]
+    vcs_root_entries: Option<VcsRootEntries>,
+    #[field_names_as_array(skip)]
+    triggers: Option<Triggers>,
+    #[field_names_as_array(skip)]
+    #[serde(rename = "snapshot-dependencies")]
+    snapshot_dependencies: Option<SnapshotDependencies>,
+}
+
+impl BuildType {
+    const DETAILS_FIELDS: &'static str = "settings(property(name,value)),\
+        parameters(property(name,value,inherited)),\
+        vcs-root-entries(vcs-root-entry(vcs-root(id,name),checkout-rules)),\
+        triggers(trigger(type,disabled,properties(property(name,value)))),\
+        snapshot-dependencies(snapshot-dependency(source-buildType(id,name)))";
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn web_url(&self) -> &str {
+        &self.web_url
+    }
+
+    pub fn kind(&self) -> &str {
+        self.r#type.as_deref().unwrap_or("regular")
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref().filter(|d| !d.is_empty())
+    }
+
+    pub fn project_name(&self) -> &str {
+        self.project_name.as_deref().unwrap_or_default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.unwrap_or_default()
+    }
+
+    pub fn settings(&self) -> &[crate::teamcity::build::Property] {
+        self.settings.as_ref().map(|s| s.property.as_slice()).unwrap_or_default()
+    }
+
+    pub fn parameters(&self) -> &[crate::teamcity::parameter::Parameter] {
+        self.parameters.as_ref().map(|p| p.property.as_slice()).unwrap_or_default()
+    }
+
+    pub fn vcs_root_entries(&self) -> &[VcsRootEntry] {
+        self.vcs_root_entries.as_ref().map(|v| v.vcs_root_entry.as_slice()).unwrap_or_default()
+    }
+
+    pub fn triggers(&self) -> &[Trigger] {
+        self.triggers.as_ref().map(|t| t.trigger.as_slice()).unwrap_or_default()
+    }
+
+    pub fn snapshot_dependencies(&self) -> &[SnapshotDependency] {
+        self.snapshot_dependencies
+            .as_ref()
+            .map(|d| d.snapshot_dependency.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VcsRoot {
+    id: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VcsRootEntry {
+    #[serde(rename = "vcs-root")]
+    vcs_root: VcsRoot,
+    #[serde(rename = "checkout-rules")]
+    checkout_rules: Option<String>,
+}
+
+impl VcsRootEntry {
+    pub fn name(&self) -> &str {
+        self.vcs_root.name.as_deref().unwrap_or(&self.vcs_root.id)
+    }
+
+    pub fn checkout_rules(&self) -> &str {
+        self.checkout_rules.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct VcsRootEntries {
+    #[serde(rename = "vcs-root-entry", default)]
+    vcs_root_entry: Vec<VcsRootEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trigger {
+    r#type: String,
+    disabled: Option<bool>,
+    properties: Option<Properties>,
+}
+
+impl Trigger {
+    pub fn kind(&self) -> &str {
+        &self.r#type
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.unwrap_or_default()
+    }
+
+    /// The trigger's settings as `name=value` pairs.
+    pub fn describe(&self) -> String {
+        self.properties
+            .iter()
+            .flat_map(|p| p.property.iter())
+            .map(|p| format!("{}={}", p.name, p.value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Triggers {
+    #[serde(default)]
+    trigger: Vec<Trigger>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct SourceBuildType {
+    id: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotDependency {
+    #[serde(rename = "source-buildType")]
+    source_build_type: SourceBuildType,
+}
+
+impl SnapshotDependency {
+    pub fn build_type_id(&self) -> &str {
+        &self.source_build_type.id
+    }
+
+    pub fn build_type_name(&self) -> &str {
+        self.source_build_type.name.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct SnapshotDependencies {
+    #[serde(rename = "snapshot-dependency", default)]
+    pub(crate) snapshot_dependency: Vec<SnapshotDependency>,
 }
 
 impl AsRef<str> for &BuildType {
@@ -40,9 +209,22 @@ pub struct BuildTypes {
     href: String,
     next_href: Option<String>,
     prev_href: Option<String>,
+    #[serde(default)]
     pub(crate) build_type: Vec<BuildType>,
 }
 
+impl Paginated for BuildTypes {
+    type Item = BuildType;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<BuildType> {
+        self.build_type
+    }
+}
+
 impl BuildTypes {
     pub fn fields() -> String {
         normalize_field_names(&BuildTypes::FIELD_NAMES_AS_ARRAY).replace(
@@ -56,17 +238,47 @@ impl BuildTypes {
 }
 
 impl<'a> Client<'a> {
-    pub async fn build_type_list(&self) -> Result<BuildTypes> {
-        let url = format!("/app/rest/buildTypes?fields={fields}", fields = BuildTypes::fields());
+    pub async fn build_type_list(&self) -> Result<Vec<BuildType>> {
+        let url = format!(
+            "/app/rest/buildTypes?locator=count:{count}&fields={fields}",
+            count = Limit::All.page_size(),
+            fields = BuildTypes::fields()
+        );
 
-        self.http_client.get(url).await
+        self.collect::<BuildTypes>(url, Limit::All).await
     }
 
-    pub async fn deployment_list(&self, build_type_id: &str) -> Result<BuildTypes> {
+    pub async fn deployment_list(&self, build_type_id: &str) -> Result<Vec<BuildType>> {
         let fields = BuildTypes::fields();
+        let count = Limit::All.page_size();
+
+        let url = format!("/app/rest/buildTypes?locator=type:deployment,project(archived:false),snapshotDependency(from:(id:{build_type_id})),count:{count}&fields={fields}");
+
+        self.collect::<BuildTypes>(url, Limit::All).await
+    }
 
-        let url = format!("/app/rest/buildTypes?locator=type:deployment,project(archived:false),snapshotDependency(from:(id:{build_type_id}))&fields={fields}");
+    /// The build type with its settings, parameters, VCS roots, triggers and dependencies.
+    pub async fn get_build_type(&self, build_type_id: Option<&str>) -> Result<BuildType> {
+        let build_type_id = build_type_id
+            .or(self.build_type)
+            .context("No build type for current repo")?;
+
+        let url = format!(
+            "/app/rest/buildTypes/id:{build_type_id}?fields={fields},{details}",
+            fields = normalize_field_names(&BuildType::FIELD_NAMES_AS_ARRAY),
+            details = BuildType::DETAILS_FIELDS
+        );
 
         self.http_client.get(url).await
     }
+
+    /// The repo's build type followed by its deployments.
+    pub async fn repo_build_type_ids(&self) -> Result<Vec<String>> {
+        let build_type = self.build_type.context("No build type for current repo")?;
+        let deployments = self.deployment_list(build_type).await?;
+
+        Ok(std::iter::once(build_type.to_string())
+            .chain(deployments.into_iter().map(|bt| bt.id))
+            .collect())
+    }
 }
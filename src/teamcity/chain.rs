@@ -0,0 +1,222 @@
+use crate::teamcity::build::{parse_timestamp, BuildRefs};
+use crate::teamcity::build_locator::{BuildLocatorBuilder, BuildState, BuildStatus};
+use crate::teamcity::build_type::SnapshotDependencies;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainBuild {
+    id: i32,
+    build_type_id: String,
+    number: Option<String>,
+    state: String,          // queued/running/finished
+    status: Option<String>, // SUCCESS/FAILURE/UNKNOWN
+    start_date: Option<String>,
+    finish_date: Option<String>,
+    #[serde(rename = "snapshot-dependencies")]
+    snapshot_dependencies: Option<BuildRefs>,
+}
+
+impl ChainBuild {
+    const FIELDS: &'static str = "nextHref,build(id,buildTypeId,number,state,status,startDate,finishDate,snapshot-dependencies(build(id)))";
+
+    pub fn build_type_id(&self) -> &str {
+        &self.build_type_id
+    }
+
+    pub fn number(&self) -> &str {
+        self.number.as_deref().unwrap_or_default()
+    }
+
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    /// How long the build took, or has been running so far.
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        let start = parse_timestamp(self.start_date.as_deref())?;
+        let finish = parse_timestamp(self.finish_date.as_deref())
+            .unwrap_or_else(|| chrono::Utc::now().fixed_offset());
+
+        Some(finish.signed_duration_since(start))
+    }
+
+    fn dependency_ids(&self) -> Vec<i32> {
+        self.snapshot_dependencies
+            .as_ref()
+            .map(|deps| deps.ids().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChainBuilds {
+    next_href: Option<String>,
+    #[serde(default)]
+    build: Vec<ChainBuild>,
+}
+
+impl Paginated for ChainBuilds {
+    type Item = ChainBuild;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<ChainBuild> {
+        self.build
+    }
+}
+
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+
+    match secs {
+        0..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Lines of the dependency tree starting at the root, as (prefix, build) pairs.
+pub fn tree_lines(builds: &[ChainBuild], root_id: i32) -> Vec<(String, &ChainBuild)> {
+    fn walk<'b>(
+        by_id: &HashMap<i32, &'b ChainBuild>,
+        id: i32,
+        indent: &str,
+        last: bool,
+        is_root: bool,
+        path: &mut Vec<i32>,
+        lines: &mut Vec<(String, &'b ChainBuild)>,
+    ) {
+        // a dependency can't depend on its dependent, but don't loop forever on bad data
+        let Some(build) = by_id.get(&id).filter(|_| !path.contains(&id)) else {
+            return;
+        };
+
+        let (prefix, child_indent) = match (is_root, last) {
+            (true, _) => (String::new(), String::new()),
+            (false, true) => (format!("{indent}└── "), format!("{indent}    ")),
+            (false, false) => (format!("{indent}├── "), format!("{indent}│   ")),
+        };
+
+        lines.push((prefix, *build));
+        path.push(id);
+
+        let dependencies = build.dependency_ids();
+
+        for (i, dependency) in dependencies.iter().enumerate() {
+            walk(by_id, *dependency, &child_indent, i + 1 == dependencies.len(), false, path, lines);
+        }
+
+        path.pop();
+    }
+
+    let by_id: HashMap<i32, &ChainBuild> = builds.iter().map(|b| (b.id, b)).collect();
+    let mut lines = Vec::new();
+
+    walk(&by_id, root_id, "", true, true, &mut Vec::new(), &mut lines);
+
+    lines
+}
+
+impl<'a> Client<'a> {
+    /// The build with all the builds it depends on, directly or transitively.
+    pub async fn get_chain(&self, build_id: i32) -> Result<Vec<ChainBuild>> {
+        let url = format!(
+            "/app/rest/builds?locator=snapshotDependency:(to:(id:{build_id}),includeInitial:true),defaultFilter:false,count:{count}&fields={fields}",
+            count = Limit::All.page_size(),
+            fields = ChainBuild::FIELDS
+        );
+
+        let builds = self.collect::<ChainBuilds>(url, Limit::All).await?;
+
+        builds
+            .iter()
+            .any(|b| b.id == build_id)
+            .then_some(builds)
+            .with_context(|| format!("Build #{build_id} is not found"))
+    }
+
+    /// Successful builds of the build type's direct snapshot dependencies made from the revision.
+    /// Only direct ones are looked up: a reused build brings its own dependencies along,
+    /// and TeamCity reuses suitable builds deeper in the chain by itself.
+    pub async fn find_reusable_dependencies(&self, build_type_id: &str, branch: &str, revision: &str) -> Result<BuildRefs> {
+        let url = format!(
+            "/app/rest/buildTypes/id:{build_type_id}/snapshot-dependencies?fields=snapshot-dependency(source-buildType(id))"
+        );
+        let dependencies: SnapshotDependencies = self.http_client.get(url).await?;
+
+        let mut ids = Vec::new();
+
+        for dependency in &dependencies.snapshot_dependency {
+            let locator = BuildLocatorBuilder::default()
+                .build_type(Some(dependency.build_type_id()))
+                .branch(Some(branch.to_owned()))
+                .revision(Some(revision))
+                .status(Some(BuildStatus::Success))
+                .state(Some(BuildState::Finished))
+                .count(Some(1))
+                .build()?;
+
+            let url = format!("/app/rest/builds?locator={locator}&fields=build(id)");
+            let found: BuildRefs = self.http_client.get(url).await?;
+
+            ids.extend(found.ids());
+        }
+
+        Ok(BuildRefs::from(ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_duration, tree_lines, ChainBuild};
+
+    fn build(id: i32, dependencies: &[i32]) -> ChainBuild {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "buildTypeId": format!("Type{id}"),
+            "state": "finished",
+            "snapshot-dependencies": {
+                "build": dependencies.iter().map(|id| serde_json::json!({ "id": id })).collect::<Vec<_>>()
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn tree_lines_test() {
+        let builds = vec![build(1, &[2, 3]), build(2, &[4]), build(3, &[4]), build(4, &[])];
+
+        let lines: Vec<(String, i32)> = tree_lines(&builds, 1)
+            .into_iter()
+            .map(|(prefix, build)| (prefix, build.id))
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                ("".to_string(), 1),
+                ("├── ".to_string(), 2),
+                ("│   └── ".to_string(), 4),
+                ("└── ".to_string(), 3),
+                ("    └── ".to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_duration_test() {
+        assert_eq!(format_duration(chrono::Duration::seconds(65)), "1m05s");
+        assert_eq!(format_duration(chrono::Duration::seconds(3 * 3600 + 120)), "3h02m");
+    }
+}
@@ -0,0 +1,313 @@
+use crate::normalize::select_one;
+use crate::teamcity::build::format_date;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::user::User;
+use crate::teamcity::Client;
+use crate::youtrack::issue::BranchNameWithIssueId;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeFile {
+    file: String,
+    change_type: Option<String>,
+}
+
+impl ChangeFile {
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// added/edited/removed/...
+    pub fn change_type(&self) -> &str {
+        self.change_type.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeFiles {
+    #[serde(default)]
+    file: Vec<ChangeFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    id: i32,
+    version: String,
+    username: Option<String>,
+    date: Option<String>,
+    comment: Option<String>,
+    files: Option<ChangeFiles>,
+    user: Option<User>,
+    parent_changes: Option<ChangeRefs>,
+}
+
+impl Change {
+    const FIELDS: &'static str = "nextHref,change(id,version,username,date,comment,files(file(file,changeType)),user(username,name,id),parentChanges(change(id)))";
+
+    pub fn short_version(&self) -> &str {
+        self.version.get(..8).unwrap_or(&self.version)
+    }
+
+    pub fn author(&self) -> &str {
+        self.user
+            .as_ref()
+            .map(|u| u.name.as_str())
+            .or(self.username.as_deref())
+            .unwrap_or_default()
+    }
+
+    pub fn date(&self) -> String {
+        format_date(self.date.as_deref())
+    }
+
+    pub fn summary(&self) -> &str {
+        self.comment
+            .as_deref()
+            .and_then(|c| c.lines().next())
+            .unwrap_or_default()
+    }
+
+    pub fn files(&self) -> &[ChangeFile] {
+        self.files.as_ref().map(|f| f.file.as_slice()).unwrap_or_default()
+    }
+
+    /// YouTrack issue id mentioned in the commit message.
+    pub fn issue_id(&self) -> Option<String> {
+        self.comment
+            .as_deref()
+            .and_then(|c| c.parse::<BranchNameWithIssueId>().ok())
+            .map(|b| b.short_name())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Changes {
+    next_href: Option<String>,
+    #[serde(default)]
+    change: Vec<Change>,
+}
+
+impl Paginated for Changes {
+    type Item = Change;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Change> {
+        self.change
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeRef {
+    id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeRefs {
+    #[serde(default)]
+    change: Vec<ChangeRef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildChangesInfo {
+    build_type_id: String,
+    branch_name: Option<String>,
+    last_changes: Option<ChangeRefs>,
+}
+
+impl BuildChangesInfo {
+    const FIELDS: &'static str = "buildTypeId,branchName,lastChanges(change(id))";
+
+    /// The head change of each VCS root of the build.
+    fn last_change_ids(&self) -> Vec<i32> {
+        self.last_changes
+            .iter()
+            .flat_map(|c| c.change.iter().map(|c| c.id))
+            .collect()
+    }
+}
+
+/// Changes reachable from the heads through their parents without passing the deployed ones,
+/// newest first. Changes of other branches aren't ancestors of the heads, whatever their ids are.
+fn undeployed_changes(changes: Vec<Change>, heads: &[i32], deployed: &[i32]) -> Vec<Change> {
+    let mut by_id: HashMap<i32, Change> = changes.into_iter().map(|c| (c.id, c)).collect();
+    let mut reachable = Vec::new();
+    let mut visited: HashSet<i32> = deployed.iter().copied().collect();
+    let mut pending: Vec<i32> = heads.to_vec();
+
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+
+        // changes older than the deployed ones aren't fetched, so the walk stops there
+        let Some(change) = by_id.remove(&id) else {
+            continue;
+        };
+
+        pending.extend(change.parent_changes.iter().flat_map(|p| p.change.iter().map(|c| c.id)));
+        reachable.push(change);
+    }
+
+    reachable.sort_by_key(|c| std::cmp::Reverse(c.id));
+    reachable
+}
+
+pub struct BuildChanges {
+    pub branch_name: Option<String>,
+    pub changes: Vec<Change>,
+}
+
+impl BuildChanges {
+    /// YouTrack issue id of the build's branch.
+    pub fn issue_id(&self) -> Option<String> {
+        self.branch_name
+            .as_deref()
+            .and_then(|b| b.parse::<BranchNameWithIssueId>().ok())
+            .map(|b| b.short_name())
+    }
+}
+
+impl<'a> Client<'a> {
+    /// Uploads the patch as a personal change, returns the change's id.
+    pub async fn upload_personal_change(&self, patch: String, description: &str) -> Result<i32> {
+        let description: String = url::form_urlencoded::byte_serialize(description.as_bytes()).collect();
+        let url = format!("/uploadDiffChanges.html?description={description}&commitType=0");
+
+        let response = self.http_client.post_text(url, patch).await?;
+
+        response
+            .trim()
+            .parse()
+            .with_context(|| format!("Unexpected response to the personal change upload: {response}"))
+    }
+
+    async fn build_changes_info(&self, build_id: i32) -> Result<BuildChangesInfo> {
+        let url = format!("/app/rest/builds/id:{build_id}?fields={fields}", fields = BuildChangesInfo::FIELDS);
+
+        self.http_client.get(url).await
+    }
+
+    async fn changes(&self, locator: &str) -> Result<Vec<Change>> {
+        let count = Limit::All.page_size();
+        let url = format!("/app/rest/changes?locator={locator},count:{count}&fields={fields}", fields = Change::FIELDS);
+
+        self.collect::<Changes>(url, Limit::All).await
+    }
+
+    /// VCS changes TeamCity attributes to the build.
+    pub async fn get_build_changes(&self, build_id: i32) -> Result<BuildChanges> {
+        let locator = format!("build:(id:{build_id})");
+
+        let (info, changes) = futures::try_join!(
+            self.build_changes_info(build_id),
+            self.changes(&locator),
+        )?;
+
+        Ok(BuildChanges {
+            branch_name: info.branch_name,
+            changes,
+        })
+    }
+
+    /// Changes of the build which are not deployed to the environment yet.
+    pub async fn get_changes_since_deployed(&self, build_id: i32, env: &str) -> Result<BuildChanges> {
+        let info = self.build_changes_info(build_id).await?;
+
+        let deployments = self.deployment_list(&info.build_type_id).await?;
+        let environment = select_one(deployments, Some(env))?;
+
+        let deployed_id = self
+            .last_deployment(&environment.id)
+            .await?
+            .and_then(|d| d.source_id())
+            .context("Nothing is deployed to the environment yet")?;
+        let deployed = self.build_changes_info(deployed_id).await?;
+
+        let deployed_ids = deployed.last_change_ids();
+        let since = deployed_ids.iter().min().context("The deployed build has no changes")?;
+
+        let branch = match info.branch_name.as_deref() {
+            Some(branch) => format!("branch:(name:{branch})"),
+            None => "branch:(default:true)".to_string(),
+        };
+
+        let changes = self
+            .changes(&format!(
+                "buildType:(id:{build_type}),{branch},sinceChange:(id:{since})",
+                build_type = info.build_type_id,
+            ))
+            .await?;
+
+        let changes = undeployed_changes(changes, &info.last_change_ids(), &deployed_ids);
+
+        Ok(BuildChanges {
+            branch_name: info.branch_name,
+            changes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{undeployed_changes, Change, ChangeRef, ChangeRefs};
+
+    fn change(comment: &str) -> Change {
+        Change {
+            id: 1,
+            version: "0123456789abcdef".into(),
+            username: Some("user".into()),
+            date: None,
+            comment: Some(comment.into()),
+            files: None,
+            user: None,
+            parent_changes: None,
+        }
+    }
+
+    fn child(id: i32, parents: &[i32]) -> Change {
+        Change {
+            id,
+            parent_changes: Some(ChangeRefs {
+                change: parents.iter().map(|&id| ChangeRef { id }).collect(),
+            }),
+            ..change("commit")
+        }
+    }
+
+    #[test]
+    fn issue_id_test() {
+        assert_eq!(change("PRJ-42 Fix login\n\nbody").issue_id(), Some("PRJ-42".into()));
+        assert_eq!(change("Merge branch 'PRJ-7-some-feature'").issue_id(), Some("PRJ-7".into()));
+        assert_eq!(change("Fix typo").issue_id(), None);
+    }
+
+    #[test]
+    fn change_accessors_test() {
+        let change = change("PRJ-42 Fix login\n\nbody");
+
+        assert_eq!(change.short_version(), "01234567");
+        assert_eq!(change.summary(), "PRJ-42 Fix login");
+        assert_eq!(change.author(), "user");
+        assert!(change.files().is_empty());
+    }
+
+    #[test]
+    fn undeployed_changes_test() {
+        // 10 is deployed, the candidate's head 14 merges 12 into 11,
+        // 13 is committed to another branch in between
+        let changes = vec![child(11, &[10]), child(12, &[10]), child(13, &[10]), child(14, &[11, 12])];
+
+        let ids: Vec<i32> = undeployed_changes(changes, &[14], &[10]).iter().map(|c| c.id).collect();
+
+        assert_eq!(ids, vec![14, 12, 11]);
+    }
+}
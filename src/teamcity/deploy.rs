@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-use crate::teamcity::build_locator::{BuildLocator, BuildLocatorBuilder};
+use crate::teamcity::build::{Properties, Property};
+use crate::teamcity::build_locator::{BuildLocator, BuildLocatorBuilder, BuildSelector};
 use crate::teamcity::Client;
-use crate::normalize::select_one;
+use crate::normalize::{select_many, select_one};
+use crate::teamcity::build_type::BuildType;
 use crate::teamcity::BuildQueue;
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use tracing::debug;
 
 #[derive(Debug, Deserialize)]
@@ -20,6 +22,18 @@ struct Build {
     status: Option<String>,
 }
 
+/// The build to deploy and the deployment build types to queue for it.
+pub struct DeployPlan {
+    build: Build,
+    pub build_types: Vec<BuildType>,
+}
+
+impl DeployPlan {
+    pub fn build_number(&self) -> &str {
+        &self.build.number
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct DeployBuild {
@@ -44,6 +58,8 @@ struct DeployBody<'a> {
     build_type: BuildTypeBody<'a>,
     #[serde(rename = "snapshot-dependencies")]
     snapshot_dependencies: DeployBuilds,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<Properties>,
 }
 
 impl<'a> Client<'a> {
@@ -61,47 +77,99 @@ impl<'a> Client<'a> {
         }
     }
 
-    pub async fn run_deploy(
+    /// The build to deploy: the one selected by `build_id`, or the last one of the branch.
+    /// A just pushed branch is checked for VCS changes first, so its build is found rather than the previous one.
+    async fn find_deploy_source(
         &self,
         build_id: Option<&str>,
-        env: Option<&str>,
         branch_name: Option<&str>,
-    ) -> Result<BuildQueue> {
-        // TODO: deploy the last master build, when build_id is "master"
-        // TODO: rerun deploy jobs
+        vcs_timeout: Option<std::time::Duration>,
+    ) -> Result<Build> {
+        let selector: Option<BuildSelector> = build_id.map(str::parse).transpose()?;
+        let pushed = self.repo.upstream_revision(branch_name).ok();
 
-        let mut locator_builder = BuildLocatorBuilder::default();
-        let id: Option<i32> = build_id.and_then(|v| v.parse().ok());
+        let locator = match &selector {
+            Some(selector) => selector.locator(self.build_type)?,
+            None => {
+                let mut locator_builder = BuildLocatorBuilder::default();
+                let branch = self.repo.normalize_branch_name(branch_name)?;
+                let build_type = self.build_type.context("No build type for current repo")?;
 
-        if id.is_some() {
-            locator_builder.id(id);
-        } else {
-            let branch = self.repo.normalize_branch_name(branch_name)?;
+                locator_builder.build_type(Some(build_type));
+                locator_builder.branch(Some(branch));
 
-            locator_builder.build_type(self.build_type);
-            locator_builder.branch(Some(branch));
+                if let Some(timeout) = vcs_timeout {
+                    self.check_for_changes(build_type, pushed.as_deref(), timeout).await?;
 
-            if branch_name.is_none() {
-                locator_builder.user(Some("current"));
+                    // the build of the pushed revision, not the previous one
+                    locator_builder.revision(pushed.as_deref());
+                }
+
+                if branch_name.is_none() {
+                    locator_builder.user(Some("current"));
+                }
+
+                locator_builder.count(Some(1));
+
+                locator_builder.build()?
             }
-        }
+        };
 
-        let locator = locator_builder.build()?;
         let build = self.get_last_build(&locator).await?;
 
         debug!("#{} {} {}", build.id, build.build_type_id, build.number);
-        let deploments = self.deployment_list(&build.build_type_id).await?;
 
-        let selected_build_type = select_one(deploments.build_type, env)?;
+        Ok(build)
+    }
+
+    /// Finds the build to deploy and its deployments matching `envs`, one per query,
+    /// or picked by hand when no queries are given.
+    pub async fn plan_deploy(
+        &self,
+        build_id: Option<&str>,
+        branch_name: Option<&str>,
+        envs: &[String],
+        vcs_timeout: Option<std::time::Duration>,
+    ) -> Result<DeployPlan> {
+        let build = self.find_deploy_source(build_id, branch_name, vcs_timeout).await?;
+        let deployments = self.deployment_list(&build.build_type_id).await?;
+
+        let build_types = if envs.is_empty() {
+            select_many(deployments, None)?
+        } else {
+            envs.iter()
+                .map(|env| select_one(deployments.clone(), Some(env)))
+                .collect::<Result<_>>()?
+        };
+
+        Ok(DeployPlan { build, build_types })
+    }
+
+    /// Queues the deployment with the plan's build as its snapshot dependency.
+    pub async fn queue_deploy(
+        &self,
+        plan: &DeployPlan,
+        build_type: &BuildType,
+        properties: Vec<Property>,
+        interactive: bool,
+    ) -> Result<BuildQueue> {
+        let build = &plan.build;
+
+        let properties = if interactive {
+            self.prompt_parameters(&build_type.id, properties).await?
+        } else {
+            properties
+        };
 
         let body = DeployBody {
             branch_name: build.branch_name.as_deref(),
             build_type: BuildTypeBody {
-                id: &selected_build_type.id,
+                id: &build_type.id,
             },
             snapshot_dependencies: DeployBuilds {
                 build: vec![DeployBuild { id: build.id }],
             },
+            properties: (!properties.is_empty()).then(|| properties.into()),
         };
 
         let response: BuildQueue = self.http_client.post("/app/rest/buildQueue", &body).await?;
@@ -0,0 +1,120 @@
+use crate::teamcity::build::format_date;
+use crate::teamcity::build_locator::{BuildLocatorBuilder, BuildState, BuildStatus};
+use crate::teamcity::build_type::BuildType;
+use crate::teamcity::user::Triggered;
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceBuild {
+    id: i32,
+    number: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SourceBuilds {
+    #[serde(default)]
+    build: Vec<SourceBuild>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    number: String,
+    branch_name: Option<String>,
+    finish_on_agent_date: Option<String>,
+    triggered: Triggered,
+    #[serde(rename = "snapshot-dependencies")]
+    snapshot_dependencies: Option<SourceBuilds>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Deployments {
+    #[serde(default)]
+    build: Vec<Deployment>,
+}
+
+impl Deployment {
+    const FIELDS: &'static str = "build(number,branchName,finishOnAgentDate,triggered(type,date,user(username,name,id)),snapshot-dependencies(build(id,number)))";
+
+    /// The deployed build (the first snapshot dependency).
+    fn source(&self) -> Option<&SourceBuild> {
+        self.snapshot_dependencies.as_ref().and_then(|deps| deps.build.first())
+    }
+
+    pub fn source_id(&self) -> Option<i32> {
+        self.source().map(|b| b.id)
+    }
+
+    pub fn build_number(&self) -> &str {
+        self.source().map(|b| b.number.as_str()).unwrap_or(&self.number)
+    }
+
+    pub fn branch_name(&self) -> Option<&str> {
+        self.branch_name.as_deref()
+    }
+
+    pub fn triggered_by(&self) -> &str {
+        self.triggered.by()
+    }
+
+    pub fn finished_at(&self) -> String {
+        format_date(self.finish_on_agent_date.as_deref())
+    }
+}
+
+pub struct Environment {
+    pub build_type: BuildType,
+    pub deployment: Option<Deployment>,
+    /// the deployed build is not the latest green master build
+    pub outdated: bool,
+}
+
+impl<'a> Client<'a> {
+    pub async fn last_deployment(&self, build_type_id: &str) -> Result<Option<Deployment>> {
+        let locator = BuildLocatorBuilder::default()
+            .build_type(Some(build_type_id))
+            .status(Some(BuildStatus::Success))
+            .state(Some(BuildState::Finished))
+            .branch(Some("any".into()))
+            .count(Some(1))
+            .build()?;
+
+        let url = format!("/app/rest/builds?locator={locator}&fields={fields}", fields = Deployment::FIELDS);
+        let deployments: Deployments = self.http_client.get(url).await?;
+
+        Ok(deployments.build.into_iter().next())
+    }
+
+    /// Deployment build types of the repo's build type with their last successful deploys.
+    pub async fn environments(&self) -> Result<Vec<Environment>> {
+        let build_type = self.build_type.context("No build type for current repo")?;
+        let deployments = self.deployment_list(build_type).await?;
+
+        let (master_id, last_deployments) = futures::try_join!(
+            self.last_green_build_id("master"),
+            futures::future::try_join_all(
+                deployments.iter().map(|bt| self.last_deployment(&bt.id))
+            ),
+        )?;
+
+        Ok(deployments
+            .into_iter()
+            .zip(last_deployments)
+            .map(|(build_type, deployment)| {
+                let outdated = match (&deployment, master_id) {
+                    (Some(deployment), Some(master_id)) => deployment.source_id() != Some(master_id),
+                    _ => false,
+                };
+
+                Environment {
+                    build_type,
+                    deployment,
+                    outdated,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,363 @@
+use crate::teamcity::build::format_date;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::user::User;
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use skim::prelude::*;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Comment {
+    user: Option<User>,
+    timestamp: Option<String>,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct IdRef {
+    id: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BuildTypeRefs {
+    #[serde(default)]
+    build_type: Vec<IdRef>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Scope {
+    build_type: Option<IdRef>,
+    build_types: Option<BuildTypeRefs>,
+    project: Option<IdRef>,
+}
+
+impl Scope {
+    fn build_type_ids(&self) -> Vec<&str> {
+        self.build_type
+            .iter()
+            .chain(self.build_types.iter().flat_map(|bts| bts.build_type.iter()))
+            .map(|bt| bt.id.as_str())
+            .collect()
+    }
+
+    /// The build types (or the project) the investigation or mute applies to.
+    pub fn describe(&self) -> String {
+        match (self.build_type_ids(), &self.project) {
+            (ids, _) if !ids.is_empty() => ids.join(", "),
+            (_, Some(project)) => format!("project {}", project.id),
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct TestRef {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct TestRefs {
+    #[serde(default)]
+    test: Vec<TestRef>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct ProblemRef {
+    identity: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct ProblemRefs {
+    #[serde(default)]
+    problem: Vec<ProblemRef>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    any_problem: Option<bool>,
+    tests: Option<TestRefs>,
+    problems: Option<ProblemRefs>,
+}
+
+impl Target {
+    /// Names of the investigated tests or problems, or the build type failure itself.
+    pub fn describe(&self) -> String {
+        let tests = self.tests.iter().flat_map(|t| t.test.iter()).map(|t| t.name.as_str());
+        let problems = self
+            .problems
+            .iter()
+            .flat_map(|p| p.problem.iter())
+            .filter_map(|p| p.identity.as_deref());
+
+        let names: Vec<&str> = tests.chain(problems).collect();
+
+        match (names.is_empty(), self.any_problem) {
+            (false, _) => names.join("\n"),
+            (true, Some(true)) => "build failure".into(),
+            (true, _) => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Investigation {
+    id: String,
+    assignee: Option<User>,
+    assignment: Option<Comment>,
+    scope: Scope,
+    target: Target,
+}
+
+impl Investigation {
+    const FIELDS: &'static str = "nextHref,investigation(id,assignee(username,name,id),assignment(user(username,name,id),timestamp,text),scope(buildType(id),buildTypes(buildType(id)),project(id)),target(anyProblem,tests(test(name)),problems(problem(identity))))";
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn assignee(&self) -> &str {
+        self.assignee.as_ref().map(|u| u.name.as_str()).unwrap_or_default()
+    }
+
+    pub fn comment(&self) -> &str {
+        self.assignment.as_ref().and_then(|a| a.text.as_deref()).unwrap_or_default()
+    }
+
+    pub fn assigned_at(&self) -> String {
+        format_date(self.assignment.as_ref().and_then(|a| a.timestamp.as_deref()))
+    }
+
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+}
+
+impl SkimItem for Investigation {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Owned(format!(
+            "{} {}",
+            self.scope.describe(),
+            self.target.describe().replace('\n', ", ")
+        ))
+    }
+
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::Text(format!("{self:#?}"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Investigations {
+    next_href: Option<String>,
+    #[serde(default)]
+    investigation: Vec<Investigation>,
+}
+
+impl Paginated for Investigations {
+    type Item = Investigation;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Investigation> {
+        self.investigation
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Mute {
+    assignment: Option<Comment>,
+    scope: Scope,
+    target: Target,
+}
+
+impl Mute {
+    const FIELDS: &'static str = "nextHref,mute(assignment(user(username,name,id),timestamp,text),scope(buildType(id),buildTypes(buildType(id)),project(id)),target(anyProblem,tests(test(name)),problems(problem(identity))))";
+
+    pub fn muted_by(&self) -> &str {
+        self.assignment
+            .as_ref()
+            .and_then(|a| a.user.as_ref())
+            .map(|u| u.name.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn comment(&self) -> &str {
+        self.assignment.as_ref().and_then(|a| a.text.as_deref()).unwrap_or_default()
+    }
+
+    pub fn muted_at(&self) -> String {
+        format_date(self.assignment.as_ref().and_then(|a| a.timestamp.as_deref()))
+    }
+
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    /// Mutes of the whole project apply to all of its build types.
+    fn applies_to(&self, build_type_ids: &[String]) -> bool {
+        let scoped = self.scope.build_type_ids();
+
+        scoped.is_empty() || scoped.iter().any(|id| build_type_ids.iter().any(|bt| bt == id))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Mutes {
+    next_href: Option<String>,
+    #[serde(default)]
+    mute: Vec<Mute>,
+}
+
+impl Paginated for Mutes {
+    type Item = Mute;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<Mute> {
+        self.mute
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectRef {
+    project_id: String,
+}
+
+/// What to do with an investigation.
+#[derive(Debug, Clone, Copy)]
+pub enum Resolution {
+    Take,
+    GiveUp,
+    Fixed,
+}
+
+impl Resolution {
+    fn state(&self) -> &'static str {
+        match self {
+            Resolution::Take => "TAKEN",
+            Resolution::GiveUp => "GIVEN_UP",
+            Resolution::Fixed => "FIXED",
+        }
+    }
+}
+
+impl<'a> Client<'a> {
+    /// Finds a user by the exact username, or picks one matching the query.
+    pub async fn resolve_user(&self, query: &str) -> Result<User> {
+        let users = self.user_list().await?;
+
+        match users.iter().find(|u| u.username == query) {
+            Some(user) => Ok(user.clone()),
+            None => crate::normalize::select_one(users, Some(query)),
+        }
+    }
+
+    /// Investigations taken by the user, the current one by default.
+    pub async fn get_investigations(&self, user: Option<&str>) -> Result<Vec<Investigation>> {
+        let user = match user {
+            Some(query) => self.resolve_user(query).await?,
+            None => self.current_user().await?,
+        };
+
+        let url = format!(
+            "/app/rest/investigations?locator=assignee:(username:{username}),state:taken,count:{count}&fields={fields}",
+            username = user.username,
+            count = Limit::All.page_size(),
+            fields = Investigation::FIELDS
+        );
+
+        self.collect::<Investigations>(url, Limit::All).await
+    }
+
+    /// Changes the state of the investigation, taking it makes the current user responsible.
+    pub async fn resolve_investigation(&self, id: &str, resolution: Resolution) -> Result<()> {
+        let url = format!("/app/rest/investigations/{id}");
+        let mut investigation: serde_json::Value = self.http_client.get(&url).await?;
+
+        investigation["state"] = resolution.state().into();
+
+        if let Resolution::Take = resolution {
+            let user = self.current_user().await?;
+            investigation["assignee"] = serde_json::json!({ "username": user.username });
+        }
+
+        self.http_client
+            .put::<_, serde_json::Value, _>(url, &investigation)
+            .await
+            .map(|_| ())
+            .with_context(|| format!("Cannot change the investigation {id}"))
+    }
+
+    /// Tests and problems muted in the repo's build types or in their whole project.
+    pub async fn get_mutes(&self) -> Result<Vec<Mute>> {
+        let build_type = self.build_type.context("No build type for current repo")?;
+
+        let (project, build_type_ids) = futures::try_join!(
+            self.http_client
+                .get::<_, ProjectRef>(format!("/app/rest/buildTypes/id:{build_type}?fields=projectId")),
+            self.repo_build_type_ids(),
+        )?;
+
+        let url = format!(
+            "/app/rest/mutes?locator=affectedProject:(id:{project}),count:{count}&fields={fields}",
+            project = project.project_id,
+            count = Limit::All.page_size(),
+            fields = Mute::FIELDS
+        );
+
+        Ok(self
+            .collect::<Mutes>(url, Limit::All)
+            .await?
+            .into_iter()
+            .filter(|mute| mute.applies_to(&build_type_ids))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mute;
+
+    fn mute(scope: serde_json::Value) -> Mute {
+        serde_json::from_value(serde_json::json!({
+            "scope": scope,
+            "target": { "tests": { "test": [{ "name": "a" }, { "name": "b" }] } },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn mute_scope_test() {
+        let ours = vec!["App_Build".to_string()];
+
+        let project_wide = mute(serde_json::json!({ "project": { "id": "App" } }));
+        assert!(project_wide.applies_to(&ours));
+        assert_eq!(project_wide.scope().describe(), "project App");
+        assert_eq!(project_wide.target().describe(), "a\nb");
+
+        let other = mute(serde_json::json!({ "buildTypes": { "buildType": [{ "id": "Other_Build" }] } }));
+        assert!(!other.applies_to(&ours));
+
+        let ours_too = mute(serde_json::json!({ "buildTypes": { "buildType": [{ "id": "Other_Build" }, { "id": "App_Build" }] } }));
+        assert!(ours_too.applies_to(&ours));
+    }
+}
@@ -1,14 +1,28 @@
+pub mod agent;
+pub mod artifact;
 pub mod build;
+pub mod build_log;
 pub mod build_locator;
 pub mod build_type;
 pub mod build_type_locator;
+pub mod chain;
+pub mod change;
 pub mod deploy;
+pub mod environment;
+pub mod investigation;
+pub mod paging;
+pub mod parameter;
+pub mod queue;
+pub mod stats;
+pub mod test_occurrence;
 pub mod user;
+pub mod vcs_root;
 
 use anyhow::{Context, Result};
 use crate::teamcity::user::Triggered;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub enum ArgBuildType {
@@ -55,15 +69,64 @@ pub struct BuildQueue {
     triggered: Triggered,
 }
 
+impl BuildQueue {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+/// A repo built by a single build type, or a monorepo whose subdirectories have their own build types, e.g.
+///
+/// ```yaml
+/// build_types:
+///   project: Project_Build
+///   monorepo:
+///     services/api: Monorepo_Api
+///     services/web: Monorepo_Web
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BuildTypeMapping {
+    Repo(String),
+    /// build types by path prefixes relative to the repo root
+    Paths(BTreeMap<String, String>),
+}
+
+impl BuildTypeMapping {
+    /// The build type of the longest prefix containing the path.
+    pub fn for_path(&self, path: &Path) -> Option<&str> {
+        match self {
+            BuildTypeMapping::Repo(build_type) => Some(build_type),
+            BuildTypeMapping::Paths(paths) => paths
+                .iter()
+                .map(|(prefix, build_type)| (Path::new(prefix.trim_start_matches("./").trim_matches('/')), build_type))
+                .filter(|(prefix, _)| path.starts_with(prefix))
+                .max_by_key(|(prefix, _)| prefix.components().count())
+                .map(|(_, build_type)| build_type.as_str()),
+        }
+    }
+
+    /// Build types of the paths, sorted and without duplicates.
+    pub fn for_paths<P: AsRef<Path>>(&self, paths: &[P]) -> Vec<&str> {
+        let mut build_types: Vec<&str> = paths.iter().filter_map(|path| self.for_path(path.as_ref())).collect();
+
+        build_types.sort();
+        build_types.dedup();
+        build_types
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TeamcitySettings {
     pub client: crate::core::config::Config,
-    pub build_types: HashMap<String, String>,
+    pub build_types: HashMap<String, BuildTypeMapping>,
 }
 
 pub struct Client<'a> {
     pub http_client: crate::core::client::Client<'a>,
     config: &'a TeamcitySettings,
+    build_type_mapping: Option<&'a BuildTypeMapping>,
+    /// the build type of the current directory
     pub build_type: Option<&'a str>,
     pub branch_name: Option<String>,
     pub repo: &'a crate::repo::Repo,
@@ -71,22 +134,100 @@ pub struct Client<'a> {
 
 impl<'a> Client<'a> {
     pub fn new(config: &'a TeamcitySettings, repo: &'a crate::repo::Repo) -> Result<Self> {
-        let build_type = repo.get_name(None).and_then(|name| { Self::default_build_type(&name, config) }).ok();
+        let build_type_mapping = repo.get_name(None).and_then(|name| Self::build_type_mapping(&name, config)).ok();
+        let build_type = match build_type_mapping {
+            Some(BuildTypeMapping::Repo(build_type)) => Some(build_type.as_str()),
+            Some(mapping) => repo.relative_path().ok().and_then(|path| mapping.for_path(&path)),
+            None => None,
+        };
 
         Ok(Self {
             http_client: crate::core::client::Client::new(&config.client)?,
             config,
+            build_type_mapping,
             build_type,
             branch_name: repo.normalize_branch_name(None).ok(),
             repo,
         })
     }
 
-    fn default_build_type(repo_name: &str, config: &'a TeamcitySettings) -> Result<&'a str> {
+    fn build_type_mapping(repo_name: &str, config: &'a TeamcitySettings) -> Result<&'a BuildTypeMapping> {
         config
             .build_types
             .get(repo_name)
-            .map(|s| s.as_str())
             .context("No build type for current repo")
     }
+
+    /// The build type of the current directory, or, outside of the mapped subdirectories of a monorepo,
+    /// the build types of the files changed by the revision (the working tree for a personal build).
+    pub fn affected_build_types(&self, branch_name: Option<&str>, revision: Option<&str>, personal: bool) -> Result<Vec<&'a str>> {
+        let mapping = self.build_type_mapping.context("No build type for current repo")?;
+
+        if let Some(build_type) = self.build_type {
+            return Ok(vec![build_type]);
+        }
+
+        // the changed files are only known for the checked out branch
+        if let Some(branch_name) = branch_name {
+            let current = self.repo.get_branch_name_meta(None)?;
+
+            if branch_name != current.local_name && Some(branch_name) != current.upstream_name.as_deref() {
+                anyhow::bail!(
+                    "Cannot tell which build types {branch_name} affects, check it out or run it from a service directory"
+                );
+            }
+        }
+
+        // the same revision as queued: without a pinned one TeamCity builds the pushed head of the branch
+        let revision = match (personal, revision) {
+            (true, _) => None,
+            (false, Some(revision)) => Some(revision.to_string()),
+            (false, None) => Some(self.repo.upstream_revision(None).context("The current branch has no upstream, push it first")?),
+        };
+
+        let paths = self
+            .repo
+            .changed_paths(revision.as_deref())
+            .context("Cannot find the files changed on the branch, run it from a service directory to build its build type")?;
+
+        let build_types = mapping.for_paths(&paths);
+
+        if build_types.is_empty() {
+            anyhow::bail!("No build type for the files changed on the branch, run it from a service directory");
+        }
+
+        Ok(build_types)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BuildTypeMapping;
+    use std::path::Path;
+
+    #[test]
+    fn build_type_mapping_test() {
+        let mapping: BuildTypeMapping = serde_yaml::from_str(
+            "
+            services/api: Mono_Api
+            services/api/admin/: Mono_Admin
+            ./services/web: Mono_Web
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(mapping.for_path(Path::new("services/api/src/main.rs")), Some("Mono_Api"));
+        assert_eq!(mapping.for_path(Path::new("services/api/admin")), Some("Mono_Admin"));
+        assert_eq!(mapping.for_path(Path::new("services/web")), Some("Mono_Web"));
+        assert_eq!(mapping.for_path(Path::new("services/api-docs/README.md")), None);
+        assert_eq!(mapping.for_path(Path::new("")), None);
+
+        assert_eq!(
+            mapping.for_paths(&["services/web/a.ts", "README.md", "services/api/b.rs", "services/web/c.ts"]),
+            vec!["Mono_Api", "Mono_Web"]
+        );
+
+        let single: BuildTypeMapping = serde_yaml::from_str("Project_Build").unwrap();
+        assert_eq!(single.for_path(Path::new("any/path")), Some("Project_Build"));
+    }
 }
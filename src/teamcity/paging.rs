@@ -0,0 +1,140 @@
+use crate::teamcity::Client;
+use anyhow::Result;
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::str::FromStr;
+
+/// A page of a TeamCity collection, which refers to the next one through `nextHref`.
+pub trait Paginated: DeserializeOwned {
+    type Item;
+
+    fn next_href(&self) -> Option<&str>;
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+/// How many items of a collection to fetch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    All,
+    Count(u32),
+}
+
+impl Limit {
+    const PAGE_SIZE: u32 = 100;
+
+    /// The `count` of a locator, so the limit is fetched in as few requests as possible.
+    pub fn page_size(&self) -> u32 {
+        match self {
+            Limit::All => Self::PAGE_SIZE,
+            Limit::Count(count) => (*count).clamp(1, Self::PAGE_SIZE),
+        }
+    }
+
+    pub fn as_usize(&self) -> usize {
+        match self {
+            Limit::All => usize::MAX,
+            Limit::Count(count) => *count as usize,
+        }
+    }
+}
+
+impl FromStr for Limit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "all" => Ok(Limit::All),
+            count => Ok(Limit::Count(count.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::All => write!(f, "all"),
+            Limit::Count(count) => write!(f, "{count}"),
+        }
+    }
+}
+
+/// Keeps the `fields` of the first request, in case `nextHref` doesn't carry them.
+fn next_url(next_href: &str, fields: Option<&str>) -> String {
+    match fields {
+        Some(fields) if !next_href.contains("fields=") => {
+            let separator = if next_href.contains('?') { '&' } else { '?' };
+
+            format!("{next_href}{separator}fields={fields}")
+        }
+        _ => next_href.to_owned(),
+    }
+}
+
+impl<'a> Client<'a> {
+    /// Lazily fetches the pages of a collection, following `nextHref` until it is exhausted.
+    pub fn paginate<'s, P>(&'s self, url: String) -> impl Stream<Item = Result<P::Item>> + 's
+    where
+        P: Paginated + 's,
+        P::Item: 's,
+    {
+        let fields = url
+            .split_once("fields=")
+            .map(|(_, fields)| fields.split('&').next().unwrap_or_default().to_owned());
+
+        futures::stream::try_unfold(Some(url), move |next| {
+            let fields = fields.clone();
+
+            async move {
+                let Some(url) = next else {
+                    return Ok::<_, anyhow::Error>(None);
+                };
+
+                let page: P = self.http_client.get(url).await?;
+                let next = page.next_href().map(|href| next_url(href, fields.as_deref()));
+                let items = futures::stream::iter(page.into_items().into_iter().map(Ok));
+
+                Ok(Some((items, next)))
+            }
+        })
+        .try_flatten()
+    }
+
+    /// Collects up to `limit` items of a collection, fetching only the pages needed.
+    pub async fn collect<P>(&self, url: String, limit: Limit) -> Result<Vec<P::Item>>
+    where
+        P: Paginated,
+    {
+        self.paginate::<P>(url).take(limit.as_usize()).try_collect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_url, Limit};
+
+    #[test]
+    fn limit_test() {
+        assert_eq!("all".parse::<Limit>().unwrap(), Limit::All);
+        assert_eq!("300".parse::<Limit>().unwrap(), Limit::Count(300));
+        assert!("many".parse::<Limit>().is_err());
+
+        assert_eq!(Limit::All.page_size(), 100);
+        assert_eq!(Limit::Count(5).page_size(), 5);
+        assert_eq!(Limit::Count(300).page_size(), 100);
+        assert_eq!(Limit::Count(300).as_usize(), 300);
+    }
+
+    #[test]
+    fn next_url_test() {
+        assert_eq!(
+            next_url("/app/rest/builds?locator=count:100,start:100", Some("build(id)")),
+            "/app/rest/builds?locator=count:100,start:100&fields=build(id)"
+        );
+        assert_eq!(
+            next_url("/app/rest/builds?locator=count:100,start:100&fields=build(id)", Some("build(id)")),
+            "/app/rest/builds?locator=count:100,start:100&fields=build(id)"
+        );
+        assert_eq!(next_url("/app/rest/users?start:100", None), "/app/rest/users?start:100");
+    }
+}
@@ -0,0 +1,285 @@
+use crate::teamcity::build::Property;
+use crate::teamcity::Client;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterType {
+    raw_value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Parameter {
+    name: String,
+    value: Option<String>,
+    r#type: Option<ParameterType>,
+    /// defined in a parent project or a template, rather than the build type itself
+    inherited: Option<bool>,
+}
+
+impl Parameter {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        self.value.as_deref().unwrap_or_default()
+    }
+
+    pub fn is_inherited(&self) -> bool {
+        self.inherited.unwrap_or_default()
+    }
+
+    pub fn spec(&self) -> Option<ParameterSpec> {
+        self.r#type.as_ref().map(|t| ParameterSpec::parse(&t.raw_value))
+    }
+
+    /// Asks for a value with a prompt matching the parameter's spec.
+    pub fn prompt(&self) -> Result<Option<Property>> {
+        let Some(spec) = self.spec() else {
+            return Ok(None);
+        };
+
+        if spec.attr("display") == Some("hidden") {
+            return Ok(None);
+        }
+
+        let label = spec.attr("label").unwrap_or(&self.name);
+        let help = spec.attr("description").unwrap_or_default();
+        let current = self.value();
+
+        let value = match spec.kind.as_str() {
+            "checkbox" => {
+                let checked = spec.attr("checkedValue").unwrap_or("true");
+                let unchecked = spec.attr("uncheckedValue").unwrap_or("false");
+
+                let answer = inquire::Confirm::new(label)
+                    .with_default(current == checked)
+                    .with_help_message(help)
+                    .prompt()?;
+
+                if answer { checked } else { unchecked }.to_string()
+            }
+            "select" if spec.attr("multiple") == Some("true") => {
+                let separator = spec.attr("valueSeparator").unwrap_or(",");
+                let options = spec.options();
+                let defaults: Vec<usize> = options
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (_, value))| current.split(separator).any(|v| v == value))
+                    .map(|(i, _)| i)
+                    .collect();
+
+                inquire::MultiSelect::new(label, options.iter().map(|(label, _)| label.clone()).collect())
+                    .with_default(&defaults)
+                    .with_help_message(help)
+                    .prompt()?
+                    .iter()
+                    .filter_map(|selected| options.iter().find(|(label, _)| label == selected))
+                    .map(|(_, value)| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            }
+            "select" => {
+                let options = spec.options();
+                let cursor = options.iter().position(|(_, value)| value == current).unwrap_or_default();
+
+                let selected = inquire::Select::new(label, options.iter().map(|(label, _)| label.clone()).collect())
+                    .with_starting_cursor(cursor)
+                    .with_help_message(help)
+                    .prompt()?;
+
+                options
+                    .into_iter()
+                    .find(|(label, _)| *label == selected)
+                    .map(|(_, value)| value)
+                    .unwrap_or(selected)
+            }
+            "password" => inquire::Password::new(label)
+                .without_confirmation()
+                .with_help_message(help)
+                .prompt()?,
+            _ => inquire::Text::new(label)
+                .with_default(current)
+                .with_help_message(help)
+                .prompt()?,
+        };
+
+        Ok(Some(Property {
+            name: self.name.clone(),
+            value,
+        }))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Parameters {
+    #[serde(default)]
+    pub property: Vec<Parameter>,
+}
+
+/// Parameter specification, e.g. `select data_1='a' data_2='Label => b' display='prompt'`.
+#[derive(Debug, PartialEq)]
+pub struct ParameterSpec {
+    kind: String,
+    attrs: HashMap<String, String>,
+}
+
+impl ParameterSpec {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        let (kind, mut rest) = raw.split_once(' ').unwrap_or((raw, ""));
+        let mut attrs = HashMap::new();
+
+        while let Some((key, tail)) = rest.trim_start().split_once("='") {
+            let mut value = String::new();
+            let mut chars = tail.char_indices();
+            let mut end = tail.len();
+
+            // values are escaped with `|`: |' for a quote, || for a pipe, |n for a new line
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '|' => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 'r')) => value.push('\r'),
+                        Some((_, escaped)) => value.push(escaped),
+                        None => (),
+                    },
+                    '\'' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+
+            attrs.insert(key.trim().to_string(), value);
+            rest = &tail[end..];
+        }
+
+        Self {
+            kind: kind.to_string(),
+            attrs,
+        }
+    }
+
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs.get(key).map(|s| s.as_str())
+    }
+
+    /// Options of a select as (label, value) pairs, in the order of `data_N` attributes.
+    pub fn options(&self) -> Vec<(String, String)> {
+        let mut data: Vec<(usize, &String)> = self
+            .attrs
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix("data_")
+                    .and_then(|n| n.parse().ok())
+                    .map(|n| (n, value))
+            })
+            .collect();
+
+        data.sort_by_key(|(n, _)| *n);
+
+        data.into_iter()
+            .map(|(_, option)| match option.split_once(" => ") {
+                Some((label, value)) => (label.trim().to_string(), value.trim().to_string()),
+                None => (option.clone(), option.clone()),
+            })
+            .collect()
+    }
+}
+
+impl<'a> Client<'a> {
+    pub async fn build_type_parameters(&self, build_type_id: &str) -> Result<Parameters> {
+        let url = format!(
+            "/app/rest/buildTypes/id:{build_type_id}/parameters?fields=property(name,value,inherited,type(rawValue))"
+        );
+
+        self.http_client.get(url).await
+    }
+
+    /// Sets the build type's own parameter, overriding the inherited one if any.
+    pub async fn set_build_type_parameter(&self, build_type_id: &str, name: &str, value: &str) -> Result<Parameter> {
+        let url = format!("/app/rest/buildTypes/id:{build_type_id}/parameters/{name}?fields=name,value,inherited");
+        let body = Property {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+
+        self.http_client.put(url, &body).await
+    }
+
+    /// Removes the build type's own parameter, inherited ones can only be changed where they are defined.
+    pub async fn unset_build_type_parameter(&self, build_type_id: &str, name: &str) -> Result<()> {
+        let parameters = self.build_type_parameters(build_type_id).await?;
+
+        match parameters.property.iter().find(|p| p.name == name) {
+            None => anyhow::bail!("{build_type_id} has no parameter {name}"),
+            Some(p) if p.is_inherited() => anyhow::bail!("{name} is inherited by {build_type_id}, change it in its project or template"),
+            Some(_) => (),
+        }
+
+        let url = format!("/app/rest/buildTypes/id:{build_type_id}/parameters/{name}");
+
+        self.http_client.delete(url).await
+    }
+
+    /// Prompts for the typed parameters of the build type, which weren't given explicitly.
+    pub async fn prompt_parameters(&self, build_type_id: &str, mut given: Vec<Property>) -> Result<Vec<Property>> {
+        let parameters = self.build_type_parameters(build_type_id).await?;
+
+        for parameter in &parameters.property {
+            if given.iter().any(|p| p.name == parameter.name()) {
+                continue;
+            }
+
+            if let Some(property) = parameter.prompt()? {
+                given.push(property);
+            }
+        }
+
+        Ok(given)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParameterSpec;
+
+    #[test]
+    fn parse_select_test() {
+        let spec = ParameterSpec::parse("select data_2='Second => 2' display='prompt' data_1='first' label='It|'s'");
+
+        assert_eq!(spec.kind, "select");
+        assert_eq!(spec.attr("display"), Some("prompt"));
+        assert_eq!(spec.attr("label"), Some("It's"));
+        assert_eq!(
+            spec.options(),
+            vec![
+                ("first".to_string(), "first".to_string()),
+                ("Second".to_string(), "2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_checkbox_test() {
+        let spec = ParameterSpec::parse("checkbox checkedValue='yes' uncheckedValue='no'");
+
+        assert_eq!(spec.kind, "checkbox");
+        assert_eq!(spec.attr("checkedValue"), Some("yes"));
+        assert_eq!(spec.attr("uncheckedValue"), Some("no"));
+        assert!(spec.options().is_empty());
+    }
+
+    #[test]
+    fn parse_text_without_attrs_test() {
+        let spec = ParameterSpec::parse("text");
+
+        assert_eq!(spec.kind, "text");
+        assert!(spec.attrs.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::teamcity::build::format_date;
+use crate::teamcity::build::BuildRef;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::user::Triggered;
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct AgentsCount {
+    count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedBuild {
+    /// 1-based position in the whole queue, not in the response
+    #[serde(skip)]
+    position: usize,
+    id: i32,
+    build_type_id: String,
+    branch_name: Option<String>,
+    web_url: String,
+    wait_reason: Option<String>,
+    start_estimate: Option<String>,
+    triggered: Triggered,
+    compatible_agents: Option<AgentsCount>,
+}
+
+impl QueuedBuild {
+    const FIELDS: &'static str = "nextHref,build(id,buildTypeId,branchName,webUrl,waitReason,startEstimate,triggered(type,date,user(username,name,id)),compatibleAgents(count))";
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn build_type_id(&self) -> &str {
+        &self.build_type_id
+    }
+
+    pub fn branch_name(&self) -> Option<&str> {
+        self.branch_name.as_deref()
+    }
+
+    pub fn web_url(&self) -> &str {
+        &self.web_url
+    }
+
+    pub fn wait_reason(&self) -> &str {
+        self.wait_reason.as_deref().unwrap_or_default()
+    }
+
+    pub fn triggered_by(&self) -> &str {
+        self.triggered.by()
+    }
+
+    fn triggered_by_user(&self, user_id: i32) -> bool {
+        self.triggered.user.as_ref().map(|u| u.id()) == Some(user_id)
+    }
+
+    pub fn compatible_agents(&self) -> Option<usize> {
+        self.compatible_agents.as_ref().map(|a| a.count)
+    }
+
+    /// When TeamCity expects the build to start.
+    pub fn start_estimate(&self) -> String {
+        format_date(self.start_estimate.as_deref())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueuedBuilds {
+    next_href: Option<String>,
+    #[serde(default)]
+    build: Vec<QueuedBuild>,
+}
+
+impl Paginated for QueuedBuilds {
+    type Item = QueuedBuild;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<QueuedBuild> {
+        self.build
+    }
+}
+
+/// Numbers the builds by their place in the queue and keeps the matching ones.
+fn positioned<F>(queue: Vec<QueuedBuild>, keep: F) -> Vec<QueuedBuild>
+where
+    F: Fn(&QueuedBuild) -> bool,
+{
+    queue
+        .into_iter()
+        .enumerate()
+        .map(|(i, build)| QueuedBuild { position: i + 1, ..build })
+        .filter(keep)
+        .collect()
+}
+
+impl<'a> Client<'a> {
+    async fn build_queue(&self) -> Result<Vec<QueuedBuild>> {
+        let url = format!(
+            "/app/rest/buildQueue?locator=count:{count}&fields={fields}",
+            count = Limit::All.page_size(),
+            fields = QueuedBuild::FIELDS
+        );
+
+        self.collect::<QueuedBuilds>(url, Limit::All).await
+    }
+
+    /// Queued builds triggered by the current user, or of the repo's build type and its deployments.
+    pub async fn get_queue(&self, mine: bool) -> Result<Vec<QueuedBuild>> {
+        if mine {
+            let (user, queue) = futures::try_join!(self.current_user(), self.build_queue())?;
+
+            return Ok(positioned(queue, |b| b.triggered_by_user(user.id())));
+        }
+
+        let (build_types, queue) = futures::try_join!(self.repo_build_type_ids(), self.build_queue())?;
+
+        Ok(positioned(queue, |b| build_types.iter().any(|id| id == b.build_type_id())))
+    }
+
+    /// Moves a build triggered by the current user to the top of the queue.
+    pub async fn move_to_top(&self, id: i32) -> Result<()> {
+        let mine = self.get_queue(true).await?;
+
+        if !mine.iter().any(|b| b.id() == id) {
+            anyhow::bail!("#{id} is not among your queued builds");
+        }
+
+        self.http_client
+            .put::<_, serde_json::Value, _>("/app/rest/buildQueue/order/1", &BuildRef { id })
+            .await
+            .map(|_| ())
+            .context("The server didn't allow moving the build to the top of the queue")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{positioned, QueuedBuild};
+
+    fn queued(id: i32, build_type_id: &str) -> QueuedBuild {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "buildTypeId": build_type_id,
+            "webUrl": "",
+            "triggered": { "type": "user", "date": "" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn positioned_test() {
+        let queue = vec![queued(1, "Other"), queued(2, "App"), queued(3, "Other"), queued(4, "App")];
+
+        let positions: Vec<(i32, usize)> = positioned(queue, |b| b.build_type_id() == "App")
+            .iter()
+            .map(|b| (b.id(), b.position()))
+            .collect();
+
+        assert_eq!(positions, vec![(2, 2), (4, 4)]);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::teamcity::build::{parse_timestamp, Properties};
+use crate::teamcity::build_locator::{BuildLocatorBuilder, BuildState};
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::Client;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Statistics of build steps, e.g. `buildStageDuration:buildStepRUNNER_1`.
+const STEP_DURATION_PREFIX: &str = "buildStageDuration:";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishedBuild {
+    status: Option<String>, // SUCCESS/FAILURE/UNKNOWN
+    queued_date: Option<String>,
+    start_date: Option<String>,
+    finish_date: Option<String>,
+    statistics: Option<Properties>,
+}
+
+impl FinishedBuild {
+    const FIELDS: &'static str = "nextHref,build(status,queuedDate,startDate,finishDate,statistics(property(name,value)))";
+
+    fn is_successful(&self) -> bool {
+        self.status.as_deref() == Some("SUCCESS")
+    }
+
+    fn day(&self) -> Option<NaiveDate> {
+        parse_timestamp(self.finish_date.as_deref()).map(|date| date.date_naive())
+    }
+
+    fn queue_secs(&self) -> Option<f64> {
+        let queued = parse_timestamp(self.queued_date.as_deref())?;
+        let started = parse_timestamp(self.start_date.as_deref())?;
+
+        Some(started.signed_duration_since(queued).num_seconds() as f64)
+    }
+
+    fn run_secs(&self) -> Option<f64> {
+        let started = parse_timestamp(self.start_date.as_deref())?;
+        let finished = parse_timestamp(self.finish_date.as_deref())?;
+
+        Some(finished.signed_duration_since(started).num_seconds() as f64)
+    }
+
+    fn statistic(&self, name: &str) -> Option<f64> {
+        self.statistics
+            .as_ref()?
+            .property
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.value.parse().ok())
+    }
+
+    /// Durations of the steps in seconds, by the step's statistic name.
+    fn step_secs(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.statistics
+            .iter()
+            .flat_map(|s| s.property.iter())
+            .filter_map(|p| {
+                let step = p.name.strip_prefix(STEP_DURATION_PREFIX)?;
+                let millis: f64 = p.value.parse().ok()?;
+
+                Some((step, millis / 1000.0))
+            })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FinishedBuilds {
+    next_href: Option<String>,
+    #[serde(default)]
+    build: Vec<FinishedBuild>,
+}
+
+impl Paginated for FinishedBuilds {
+    type Item = FinishedBuild;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<FinishedBuild> {
+        self.build
+    }
+}
+
+/// The value below which `p` percent of the values fall (nearest-rank).
+pub fn percentile(values: &[f64], p: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+
+    sorted.get(rank.clamp(1, sorted.len()) - 1).copied()
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    percentile(values, 50.0)
+}
+
+/// Aggregates of the builds of one day.
+pub struct DayStats {
+    pub day: NaiveDate,
+    pub builds: usize,
+    pub success_rate: f64,
+    pub run_secs: Option<f64>,
+    pub queue_secs: Option<f64>,
+    /// medians of the requested statistics, in the requested order
+    pub statistics: Vec<Option<f64>>,
+}
+
+pub struct Stats {
+    pub builds: usize,
+    pub success_rate: f64,
+    pub run_secs: Vec<f64>,
+    pub queue_secs: Vec<f64>,
+    /// the steps with the longest median duration, slowest first
+    pub slowest_steps: Vec<(String, f64)>,
+    pub days: Vec<DayStats>,
+}
+
+fn success_rate(builds: &[&FinishedBuild]) -> f64 {
+    match builds.len() {
+        0 => 0.0,
+        count => builds.iter().filter(|b| b.is_successful()).count() as f64 * 100.0 / count as f64,
+    }
+}
+
+impl Stats {
+    const SLOWEST_STEPS: usize = 3;
+
+    pub fn new(builds: &[FinishedBuild], statistics: &[String]) -> Self {
+        let all: Vec<&FinishedBuild> = builds.iter().collect();
+
+        let mut steps: HashMap<&str, Vec<f64>> = HashMap::new();
+
+        for (step, secs) in builds.iter().flat_map(|b| b.step_secs()) {
+            steps.entry(step).or_default().push(secs);
+        }
+
+        let mut slowest_steps: Vec<(String, f64)> = steps
+            .into_iter()
+            .filter_map(|(step, secs)| median(&secs).map(|m| (step.to_string(), m)))
+            .collect();
+
+        slowest_steps.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        slowest_steps.truncate(Self::SLOWEST_STEPS);
+
+        let mut by_day: BTreeMap<NaiveDate, Vec<&FinishedBuild>> = BTreeMap::new();
+
+        for build in builds {
+            if let Some(day) = build.day() {
+                by_day.entry(day).or_default().push(build);
+            }
+        }
+
+        let days = by_day
+            .into_iter()
+            .map(|(day, builds)| DayStats {
+                day,
+                builds: builds.len(),
+                success_rate: success_rate(&builds),
+                run_secs: median(&builds.iter().filter_map(|b| b.run_secs()).collect::<Vec<_>>()),
+                queue_secs: median(&builds.iter().filter_map(|b| b.queue_secs()).collect::<Vec<_>>()),
+                statistics: statistics
+                    .iter()
+                    .map(|name| median(&builds.iter().filter_map(|b| b.statistic(name)).collect::<Vec<_>>()))
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            builds: builds.len(),
+            success_rate: success_rate(&all),
+            run_secs: builds.iter().filter_map(|b| b.run_secs()).collect(),
+            queue_secs: builds.iter().filter_map(|b| b.queue_secs()).collect(),
+            slowest_steps,
+            days,
+        }
+    }
+}
+
+impl<'a> Client<'a> {
+    /// Recent finished builds of the build type (the repo's one by default) on the branch.
+    pub async fn get_finished_builds(&self, build_type: Option<&str>, branch_name: Option<&str>, limit: Limit) -> Result<Vec<FinishedBuild>> {
+        let build_type = build_type
+            .or(self.build_type)
+            .context("No build type for current repo")?;
+
+        let locator = BuildLocatorBuilder::default()
+            .build_type(Some(build_type))
+            .branch(Some(self.repo.normalize_branch_name(branch_name)?))
+            .state(Some(BuildState::Finished))
+            .personal(Some(false))
+            .count(Some(limit.page_size()))
+            .build()?;
+
+        let url = format!("/app/rest/builds?locator={locator}&fields={fields}", fields = FinishedBuild::FIELDS);
+
+        self.collect::<FinishedBuilds>(url, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{percentile, FinishedBuild, Stats};
+
+    fn build(status: &str, day: u32, run_mins: u32, coverage: &str) -> FinishedBuild {
+        serde_json::from_value(serde_json::json!({
+            "status": status,
+            "queuedDate": format!("202301{day:02}T095900+0000"),
+            "startDate": format!("202301{day:02}T100000+0000"),
+            "finishDate": format!("202301{day:02}T10{run_mins:02}00+0000"),
+            "statistics": { "property": [
+                { "name": "CodeCoverageL%", "value": coverage },
+                { "name": "buildStageDuration:buildStepRUNNER_1", "value": "60000" },
+                { "name": "buildStageDuration:buildStepRUNNER_2", "value": "120000" },
+            ] },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn percentile_test() {
+        let values = [5.0, 1.0, 3.0, 2.0, 4.0];
+
+        assert_eq!(percentile(&values, 50.0), Some(3.0));
+        assert_eq!(percentile(&values, 95.0), Some(5.0));
+        assert_eq!(percentile(&values, 0.0), Some(1.0));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn stats_test() {
+        let builds = vec![
+            build("SUCCESS", 1, 10, "80.5"),
+            build("FAILURE", 1, 20, "80.0"),
+            build("SUCCESS", 2, 30, "81.0"),
+            build("SUCCESS", 3, 40, "82.0"),
+        ];
+
+        let stats = Stats::new(&builds, &["CodeCoverageL%".to_string()]);
+
+        assert_eq!(stats.builds, 4);
+        assert_eq!(stats.success_rate, 75.0);
+        assert_eq!(stats.queue_secs, vec![60.0; 4]);
+        assert_eq!(stats.slowest_steps[0], ("buildStepRUNNER_2".to_string(), 120.0));
+
+        assert_eq!(stats.days.len(), 3);
+        assert_eq!(stats.days[0].builds, 2);
+        assert_eq!(stats.days[0].success_rate, 50.0);
+        assert_eq!(stats.days[0].statistics, vec![Some(80.0)]);
+        assert_eq!(stats.days[2].run_secs, Some(40.0 * 60.0));
+    }
+}
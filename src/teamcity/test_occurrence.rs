@@ -0,0 +1,185 @@
+use crate::normalize::normalize_field_names;
+use crate::teamcity::build_locator::BuildLocatorBuilder;
+use crate::teamcity::paging::{Limit, Paginated};
+use crate::teamcity::Client;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashSet;
+use struct_field_names_as_array::FieldNamesAsArray;
+
+#[derive(Debug, Deserialize, FieldNamesAsArray)]
+#[serde(rename_all = "camelCase")]
+#[field_names_as_array(rename_all = "camelCase")]
+pub struct TestOccurrence {
+    name: String,
+    status: String, // SUCCESS/FAILURE/UNKNOWN
+    muted: Option<bool>,
+    ignored: Option<bool>,
+    details: Option<String>,
+}
+
+impl TestOccurrence {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn details(&self) -> Option<&str> {
+        self.details.as_deref()
+    }
+
+    /// failed/muted/ignored/passed
+    pub fn kind(&self) -> &str {
+        if self.muted.unwrap_or_default() {
+            "muted"
+        } else if self.ignored.unwrap_or_default() {
+            "ignored"
+        } else if self.status == "FAILURE" {
+            "failed"
+        } else {
+            "passed"
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, FieldNamesAsArray)]
+#[serde(rename_all = "camelCase")]
+#[field_names_as_array(rename_all = "camelCase")]
+pub struct TestOccurrences {
+    next_href: Option<String>,
+    #[serde(default)]
+    test_occurrence: Vec<TestOccurrence>,
+}
+
+impl Paginated for TestOccurrences {
+    type Item = TestOccurrence;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<TestOccurrence> {
+        self.test_occurrence
+    }
+}
+
+impl TestOccurrences {
+    pub fn fields() -> String {
+        normalize_field_names(&TestOccurrences::FIELD_NAMES_AS_ARRAY).replace(
+            "testOccurrence",
+            &format!(
+                "testOccurrence({})",
+                normalize_field_names(&TestOccurrence::FIELD_NAMES_AS_ARRAY)
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildRef {
+    id: i32,
+    build_type_id: String,
+    branch_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildRefs {
+    #[serde(default)]
+    build: Vec<BuildRef>,
+}
+
+/// Tests failing in `current` which didn't fail in `previous`.
+pub fn new_failures(current: Vec<TestOccurrence>, previous: &[TestOccurrence]) -> Vec<TestOccurrence> {
+    let failed_before: HashSet<&str> = previous
+        .iter()
+        .filter(|t| t.kind() == "failed")
+        .map(|t| t.name())
+        .collect();
+
+    current
+        .into_iter()
+        .filter(|t| t.kind() == "failed" && !failed_before.contains(t.name()))
+        .collect()
+}
+
+impl<'a> Client<'a> {
+    async fn test_occurrences(&self, locator: &str) -> Result<Vec<TestOccurrence>> {
+        let fields = TestOccurrences::fields();
+        let count = Limit::All.page_size();
+        let url = format!("/app/rest/testOccurrences?locator={locator},count:{count}&fields={fields}");
+
+        self.collect::<TestOccurrences>(url, Limit::All).await
+    }
+
+    /// Failed (including muted) and ignored tests of the build.
+    pub async fn get_failed_tests(&self, build_id: i32) -> Result<Vec<TestOccurrence>> {
+        let mut tests = self
+            .test_occurrences(&format!("build:(id:{build_id}),status:FAILURE"))
+            .await?;
+
+        tests.extend(
+            self.test_occurrences(&format!("build:(id:{build_id}),ignored:true"))
+                .await?,
+        );
+
+        Ok(tests)
+    }
+
+    /// The last finished build on the same branch and build type, preceding the given one.
+    pub async fn get_previous_build_id(&self, build_id: i32) -> Result<Option<i32>> {
+        let url = format!("/app/rest/builds/id:{build_id}?fields=id,buildTypeId,branchName");
+        let build: BuildRef = self.http_client.get(url).await?;
+
+        let locator = BuildLocatorBuilder::default()
+            .build_type(Some(build.build_type_id.as_str()))
+            .branch(Some(build.branch_name.unwrap_or_else(|| "(default:true)".into())))
+            .until_build(Some(build.id))
+            .personal(Some(false))
+            .count(Some(2))
+            .build()?;
+
+        let url = format!("/app/rest/builds?locator={locator}&fields=build(id,buildTypeId,branchName)");
+        let response: BuildRefs = self.http_client.get(url).await?;
+
+        Ok(response
+            .build
+            .into_iter()
+            .map(|b| b.id)
+            .find(|id| *id != build.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{new_failures, TestOccurrence};
+
+    fn occurrence(name: &str, status: &str, muted: bool) -> TestOccurrence {
+        TestOccurrence {
+            name: name.to_string(),
+            status: status.to_string(),
+            muted: Some(muted),
+            ignored: None,
+            details: None,
+        }
+    }
+
+    #[test]
+    fn new_failures_test() {
+        let previous = vec![
+            occurrence("a", "FAILURE", false),
+            occurrence("b", "SUCCESS", false),
+        ];
+        let current = vec![
+            occurrence("a", "FAILURE", false),
+            occurrence("b", "FAILURE", false),
+            occurrence("c", "FAILURE", true),
+        ];
+
+        let names: Vec<String> = new_failures(current, &previous)
+            .iter()
+            .map(|t| t.name().to_string())
+            .collect();
+
+        assert_eq!(names, vec!["b"]);
+    }
+}
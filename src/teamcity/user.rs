@@ -1,3 +1,4 @@
+use crate::teamcity::paging::{Limit, Paginated};
 use crate::teamcity::Client;
 use crate::normalize::*;
 use anyhow::Result;
@@ -24,7 +25,7 @@ impl SkimItem for User {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, FieldNamesAsArray)]
+#[derive(Debug, Serialize, Deserialize, Clone, FieldNamesAsArray)]
 #[serde(rename_all = "camelCase")]
 #[field_names_as_array(visibility = "pub")]
 pub struct Triggered {
@@ -33,13 +34,38 @@ pub struct Triggered {
     pub user: Option<User>,
 }
 
+impl Triggered {
+    /// The user's name, or the trigger type when it wasn't triggered manually.
+    pub fn by(&self) -> &str {
+        match &self.user {
+            Some(user) => user.name.as_str(),
+            None => self.r#type.as_str(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FieldNamesAsArray)]
 #[serde(rename_all = "camelCase")]
+#[field_names_as_array(rename_all = "camelCase")]
 pub struct Users {
     count: i32,
+    next_href: Option<String>,
+    #[serde(default)]
     pub(crate) user: Vec<User>,
 }
 
+impl Paginated for Users {
+    type Item = User;
+
+    fn next_href(&self) -> Option<&str> {
+        self.next_href.as_deref()
+    }
+
+    fn into_items(self) -> Vec<User> {
+        self.user
+    }
+}
+
 impl Users {
     pub fn fields() -> String {
         normalize_field_names(&Users::FIELD_NAMES_AS_ARRAY).replace(
@@ -52,12 +78,35 @@ impl Users {
     }
 }
 
+impl User {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
 impl<'a> Client<'a> {
-    pub async fn user_list(&self) -> Result<Users> {
+    /// The user associated with the token.
+    pub async fn current_user(&self) -> Result<User> {
+        let fields = normalize_field_names(&User::FIELD_NAMES_AS_ARRAY);
+
+        self.http_client.get(format!("/app/rest/users/current?fields={fields}")).await
+    }
+
+    pub async fn user_list(&self) -> Result<Vec<User>> {
         let fields = Users::fields();
-        let url = format!("/app/rest/users?fields={fields}");
-        let response: Users = self.http_client.get(url).await?;
+        let count = Limit::All.page_size();
+        let url = format!("/app/rest/users?locator=count:{count}&fields={fields}");
+
+        self.collect::<Users>(url, Limit::All).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Users;
 
-        Ok(response)
+    #[test]
+    fn users_fields_test() {
+        assert_eq!(Users::fields(), "count,nextHref,user(username,name,id)");
     }
 }
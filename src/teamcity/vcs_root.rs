@@ -0,0 +1,62 @@
+use crate::teamcity::Client;
+use anyhow::Result;
+use console::style;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+struct ChangesCount {
+    count: usize,
+}
+
+impl<'a> Client<'a> {
+    /// Asks TeamCity to check the VCS roots of the build type for new commits right away.
+    async fn request_vcs_check(&self, build_type_id: &str) -> Result<()> {
+        let url = format!("/app/rest/vcs-root-instances/checkingForChangesQueue?locator=buildType:(id:{build_type_id})");
+
+        self.http_client
+            .post::<_, serde_json::Value, _>(url, &())
+            .await
+            .map(|_| ())
+    }
+
+    /// Whether TeamCity has detected the commit in any branch of the build type.
+    async fn has_revision(&self, build_type_id: &str, revision: &str) -> Result<bool> {
+        let url = format!(
+            "/app/rest/changes?locator=buildType:(id:{build_type_id}),version:{revision},branch:(default:any),count:1&fields=count"
+        );
+        let changes: ChangesCount = self.http_client.get(url).await?;
+
+        Ok(changes.count > 0)
+    }
+
+    /// Requests a VCS check and waits until TeamCity reports the revision, when it is given.
+    pub async fn check_for_changes(&self, build_type_id: &str, revision: Option<&str>, timeout: Duration) -> Result<()> {
+        eprintln!("{}", style(format!("Checking {build_type_id} for VCS changes…")).dim());
+
+        self.request_vcs_check(build_type_id).await?;
+
+        let Some(revision) = revision else {
+            return Ok(());
+        };
+
+        let started = Instant::now();
+
+        while !self.has_revision(build_type_id, revision).await? {
+            if started.elapsed() >= timeout {
+                anyhow::bail!(
+                    "TeamCity hasn't detected {revision} in {}s, make sure it is pushed",
+                    timeout.as_secs()
+                );
+            }
+
+            debug!("waiting for {revision} in {build_type_id}");
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+
+        Ok(())
+    }
+}